@@ -0,0 +1,576 @@
+use crate::parse::{Data, DefaultValue, Field, Fields, Input, Variant};
+
+const VALUE: &str = "::monkey_json::Value";
+const MAP: &str = "::monkey_json::Map";
+const ERROR: &str = "::monkey_json::ConvertError";
+const FROM_VALUE: &str = "::monkey_json::FromValue";
+const TO_VALUE: &str = "::monkey_json::ToValue";
+const TO_OBJECT: &str = "::monkey_json::ToObject";
+const RESULT: &str = "::std::result::Result";
+
+/// `#[derive(FromJson)]`の`impl`を生成する
+pub fn expand_from_json(input: &Input) -> Result<String, String> {
+    input.attrs.check(&["tag"], "a type")?;
+    let body = match &input.data {
+        Data::Struct(_) if input.attrs.tag.is_some() => {
+            return Err("`#[json(tag = \"...\")]` is only supported on enums".to_string())
+        }
+        Data::Struct(fields) => from_fields("Self", fields, "")?,
+        Data::Enum(variants) => match &input.attrs.tag {
+            Some(tag) => from_internally_tagged(tag, variants)?,
+            None => from_externally_tagged(variants)?,
+        },
+    };
+    Ok(format!(
+        "#[automatically_derived]
+        impl{impl_generics} {FROM_VALUE} for {name}{ty_generics} {where_clause} {{
+            fn from_value(value: &{VALUE}) -> {RESULT}<Self, {ERROR}> {{
+                {body}
+            }}
+        }}",
+        impl_generics = input.generics.impl_generics(FROM_VALUE),
+        name = input.name,
+        ty_generics = input.generics.ty_generics(),
+        where_clause = input.generics.where_clause(),
+        body = body,
+        FROM_VALUE = FROM_VALUE,
+        VALUE = VALUE,
+        RESULT = RESULT,
+        ERROR = ERROR,
+    ))
+}
+
+/// `#[derive(ToJson)]`の`impl`を生成する
+/// 常に`Object`に変換される型(名前付きフィールドの`struct`とinternally taggedの`enum`)は`ToObject`も実装する
+pub fn expand_to_json(input: &Input) -> Result<String, String> {
+    input.attrs.check(&["tag"], "a type")?;
+    let object_body = match &input.data {
+        Data::Struct(_) if input.attrs.tag.is_some() => {
+            return Err("`#[json(tag = \"...\")]` is only supported on enums".to_string())
+        }
+        Data::Struct(fields @ Fields::Named(named)) => {
+            check_fields(fields)?;
+            let accessors = named
+                .iter()
+                .map(|f| format!("&self.{}", f.ident.as_ref().expect("a named field")))
+                .collect::<Vec<_>>();
+            Some(format!(
+                "let mut object = {MAP}::new(); {statements} object",
+                statements = to_named_fields(named, &accessors),
+                MAP = MAP,
+            ))
+        }
+        Data::Enum(variants) => match &input.attrs.tag {
+            Some(tag) => {
+                let mut arms = vec![];
+                for variant in variants {
+                    let (pattern, accessors) = variant_pattern(variant);
+                    let name = variant_name(variant)?;
+                    let body = to_internally_tagged(tag, &name, variant, &accessors)?;
+                    arms.push(format!("{} => {{ {} }}", pattern, body));
+                }
+                Some(match_variants(&arms))
+            }
+            None => None,
+        },
+        Data::Struct(_) => None,
+    };
+    let body = match (&input.data, &object_body) {
+        (_, Some(_)) => format!(
+            "{}::Object(<Self as {}>::to_object(self))",
+            VALUE, TO_OBJECT
+        ),
+        (Data::Struct(fields), None) => {
+            let accessors = match fields {
+                Fields::Unnamed(fields) => {
+                    (0..fields.len()).map(|i| format!("&self.{}", i)).collect()
+                }
+                _ => vec![],
+            };
+            to_fields(fields, &accessors)?
+        }
+        (Data::Enum(variants), None) => {
+            let mut arms = vec![];
+            for variant in variants {
+                let (pattern, accessors) = variant_pattern(variant);
+                let name = variant_name(variant)?;
+                let body = to_externally_tagged(&name, variant, &accessors)?;
+                arms.push(format!("{} => {{ {} }}", pattern, body));
+            }
+            match_variants(&arms)
+        }
+    };
+    let mut code = format!(
+        "#[automatically_derived]
+        impl{impl_generics} {TO_VALUE} for {name}{ty_generics} {where_clause} {{
+            fn to_value(&self) -> {VALUE} {{
+                {body}
+            }}
+        }}",
+        impl_generics = input.generics.impl_generics(TO_VALUE),
+        name = input.name,
+        ty_generics = input.generics.ty_generics(),
+        where_clause = input.generics.where_clause(),
+        body = body,
+        TO_VALUE = TO_VALUE,
+        VALUE = VALUE,
+    );
+    if let Some(object_body) = object_body {
+        code.push_str(&format!(
+            "#[automatically_derived]
+            impl{impl_generics} {TO_OBJECT} for {name}{ty_generics} {where_clause} {{
+                fn to_object(&self) -> {MAP} {{
+                    {body}
+                }}
+            }}",
+            impl_generics = input.generics.impl_generics(TO_VALUE),
+            name = input.name,
+            ty_generics = input.generics.ty_generics(),
+            where_clause = input.generics.where_clause(),
+            body = object_body,
+            TO_OBJECT = TO_OBJECT,
+            MAP = MAP,
+        ));
+    }
+    Ok(code)
+}
+
+/// `match self`で各variantの式を選ぶ式を返す
+fn match_variants(arms: &[String]) -> String {
+    if arms.is_empty() {
+        "match *self {}".to_string()
+    } else {
+        format!("match self {{ {} }}", arms.join(",\n"))
+    }
+}
+
+/// JSONのkeyを返す。`r#type`のようなraw identifierは`type`にする
+fn field_name(field: &Field) -> String {
+    match &field.attrs.rename {
+        Some(rename) => rename.clone(),
+        None => {
+            let ident = field.ident.as_ref().expect("a named field");
+            ident.trim_start_matches("r#").to_string()
+        }
+    }
+}
+
+fn variant_name(variant: &Variant) -> Result<String, String> {
+    variant.attrs.check(&["rename"], "a variant")?;
+    Ok(variant
+        .attrs
+        .rename
+        .clone()
+        .unwrap_or_else(|| variant.ident.trim_start_matches("r#").to_string()))
+}
+
+/// フィールドの属性を検査する
+fn check_fields(fields: &Fields) -> Result<(), String> {
+    match fields {
+        Fields::Named(fields) => fields.iter().try_for_each(|f| {
+            f.attrs
+                .check(&["rename", "default", "skip", "flatten"], "a field")
+        }),
+        Fields::Unnamed(fields) => fields
+            .iter()
+            .try_for_each(|f| f.attrs.check(&[], "a tuple field")),
+        Fields::Unit => Ok(()),
+    }
+}
+
+/// `#[json(default)]`の値を返す式
+fn default_expr(default: &DefaultValue) -> String {
+    match default {
+        DefaultValue::Trait => "::std::default::Default::default()".to_string(),
+        DefaultValue::Path(path) => format!("{}()", path),
+    }
+}
+
+/// `e`にエラーのパスを追加する式を返す。`wrap`が空なら`e`をそのまま返す
+/// e.g. wrap = `.at_key("A")`
+fn map_err(wrap: &str) -> String {
+    if wrap.is_empty() {
+        String::new()
+    } else {
+        format!(".map_err(|e| e{})", wrap)
+    }
+}
+
+/// `value`から`ctor`を作る式を返す。`wrap`はエラーのパスに追加する呼び出し
+fn from_fields(ctor: &str, fields: &Fields, wrap: &str) -> Result<String, String> {
+    check_fields(fields)?;
+    let expr = match fields {
+        Fields::Named(fields) => format!(
+            "let object = value.as_object().ok_or_else(|| {ERROR}::invalid_type(\"object\", value){wrap})?;
+            {RESULT}::Ok({named})",
+            named = from_named_fields(ctor, fields, wrap),
+            wrap = wrap,
+            ERROR = ERROR,
+            RESULT = RESULT,
+        ),
+        // 要素が1つの場合は中身をそのまま変換する
+        Fields::Unnamed(fields) if fields.len() == 1 => format!(
+            "{RESULT}::Ok({ctor}(<{ty} as {FROM_VALUE}>::from_value(value){map_err}?))",
+            ctor = ctor,
+            ty = fields[0].ty,
+            map_err = map_err(wrap),
+            FROM_VALUE = FROM_VALUE,
+            RESULT = RESULT,
+        ),
+        Fields::Unnamed(fields) => {
+            let elements = fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| {
+                    format!(
+                        "<{ty} as {FROM_VALUE}>::from_value(&array[{i}]).map_err(|e| e.at_index({i}){wrap})?",
+                        ty = f.ty,
+                        i = i,
+                        wrap = wrap,
+                        FROM_VALUE = FROM_VALUE,
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "let array = value.as_array().ok_or_else(|| {ERROR}::invalid_type(\"array\", value){wrap})?;
+                if array.len() != {len} {{
+                    return {RESULT}::Err({ERROR}::custom(::std::format!(
+                        \"expected an array of length {{}}, found length {{}}\", {len}, array.len()
+                    )){wrap});
+                }}
+                {RESULT}::Ok({ctor}({elements}))",
+                ctor = ctor,
+                len = fields.len(),
+                elements = elements.join(", "),
+                wrap = wrap,
+                ERROR = ERROR,
+                RESULT = RESULT,
+            )
+        }
+        Fields::Unit => format!(
+            "match value {{
+                {VALUE}::Null => {RESULT}::Ok({ctor}),
+                _ => {RESULT}::Err({ERROR}::invalid_type(\"null\", value){wrap}),
+            }}",
+            ctor = ctor,
+            wrap = wrap,
+            VALUE = VALUE,
+            ERROR = ERROR,
+            RESULT = RESULT,
+        ),
+    };
+    Ok(format!("{{ {} }}", expr))
+}
+
+/// `object`と`value`から名前付きフィールドの`ctor`を作る式を返す
+fn from_named_fields(ctor: &str, fields: &[Field], wrap: &str) -> String {
+    let fields = fields
+        .iter()
+        .map(|f| {
+            let ident = f.ident.as_ref().expect("a named field");
+            let key = field_name(f);
+            let expr = if f.attrs.skip {
+                default_expr(f.attrs.default.as_ref().unwrap_or(&DefaultValue::Trait))
+            } else if f.attrs.flatten {
+                // 同じ`Object`から変換する
+                format!(
+                    "<{ty} as {FROM_VALUE}>::from_value(value){map_err}?",
+                    ty = f.ty,
+                    map_err = map_err(wrap),
+                    FROM_VALUE = FROM_VALUE,
+                )
+            } else {
+                // keyがない場合は`Null`からの変換を試みる(`Option`なら`None`になる)
+                let missing = match &f.attrs.default {
+                    Some(default) => default_expr(default),
+                    None => format!(
+                        "match <{ty} as {FROM_VALUE}>::from_value(&{VALUE}::Null) {{
+                            {RESULT}::Ok(v) => v,
+                            {RESULT}::Err(_) => return {RESULT}::Err({ERROR}::missing_field({key:?}){wrap}),
+                        }}",
+                        ty = f.ty,
+                        key = key,
+                        wrap = wrap,
+                        FROM_VALUE = FROM_VALUE,
+                        VALUE = VALUE,
+                        ERROR = ERROR,
+                        RESULT = RESULT,
+                    ),
+                };
+                format!(
+                    "match object.get({key:?}) {{
+                        ::std::option::Option::Some(v) => <{ty} as {FROM_VALUE}>::from_value(v)
+                            .map_err(|e| e.at_key({key:?}){wrap})?,
+                        ::std::option::Option::None => {missing},
+                    }}",
+                    key = key,
+                    ty = f.ty,
+                    wrap = wrap,
+                    missing = missing,
+                    FROM_VALUE = FROM_VALUE,
+                )
+            };
+            format!("{}: {}", ident, expr)
+        })
+        .collect::<Vec<_>>();
+    format!("{} {{ {} }}", ctor, fields.join(",\n"))
+}
+
+/// e.g. "A" or {"B": 1}
+fn from_externally_tagged(variants: &[Variant]) -> Result<String, String> {
+    let mut unit_arms = vec![];
+    let mut content_arms = vec![];
+    for variant in variants {
+        let name = variant_name(variant)?;
+        let ctor = format!("Self::{}", variant.ident);
+        match &variant.fields {
+            Fields::Unit => unit_arms.push(format!("{:?} => {}::Ok({}),", name, RESULT, ctor)),
+            fields => {
+                let wrap = format!(".at_key({:?})", name);
+                content_arms.push(format!(
+                    "{:?} => {{ let value = content; {} }}",
+                    name,
+                    from_fields(&ctor, fields, &wrap)?
+                ));
+            }
+        }
+    }
+    let unknown = format!(
+        "_ => {}::Err({}::custom(::std::format!(\"unknown variant {{:?}}\", tag))),",
+        RESULT, ERROR
+    );
+    let content_branch = if content_arms.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "{VALUE}::Object(object) if object.len() == 1 => {{
+                let (tag, content) = object.iter().next().expect(\"an object has one entry\");
+                match tag.as_str() {{
+                    {arms}
+                    {unknown}
+                }}
+            }}",
+            arms = content_arms.join("\n"),
+            unknown = unknown,
+            VALUE = VALUE,
+        )
+    };
+    Ok(format!(
+        "match value {{
+            {VALUE}::String(tag) => match tag.as_str() {{
+                {unit_arms}
+                {unknown}
+            }},
+            {content_branch}
+            _ => {RESULT}::Err({ERROR}::invalid_type(\"string or object\", value)),
+        }}",
+        unit_arms = unit_arms.join("\n"),
+        unknown = unknown,
+        content_branch = content_branch,
+        VALUE = VALUE,
+        ERROR = ERROR,
+        RESULT = RESULT,
+    ))
+}
+
+/// e.g. {"type": "A", "a": 1}
+fn from_internally_tagged(tag: &str, variants: &[Variant]) -> Result<String, String> {
+    let mut arms = vec![];
+    for variant in variants {
+        let name = variant_name(variant)?;
+        let ctor = format!("Self::{}", variant.ident);
+        match &variant.fields {
+            Fields::Unnamed(fields) if fields.len() != 1 => {
+                return Err(format!(
+                    "the tuple variant `{}` cannot be internally tagged",
+                    variant.ident
+                ))
+            }
+            // `Object`には`tag`しかない
+            Fields::Unit => arms.push(format!("{:?} => {}::Ok({}),", name, RESULT, ctor)),
+            fields => arms.push(format!(
+                "{:?} => {},",
+                name,
+                from_fields(&ctor, fields, "")?
+            )),
+        }
+    }
+    Ok(format!(
+        "let object = value.as_object().ok_or_else(|| {ERROR}::invalid_type(\"object\", value))?;
+        let tag = match object.get({tag:?}) {{
+            ::std::option::Option::Some(tag) => tag
+                .as_str()
+                .ok_or_else(|| {ERROR}::invalid_type(\"string\", tag).at_key({tag:?}))?,
+            ::std::option::Option::None => return {RESULT}::Err({ERROR}::missing_field({tag:?})),
+        }};
+        match tag {{
+            {arms}
+            _ => {RESULT}::Err({ERROR}::custom(::std::format!(\"unknown variant {{:?}}\", tag)).at_key({tag:?})),
+        }}",
+        tag = tag,
+        arms = arms.join("\n"),
+        ERROR = ERROR,
+        RESULT = RESULT,
+    ))
+}
+
+/// `match self`のパターンと各フィールドを参照する変数名を返す
+/// e.g. Self::A { a: __field0, b: _ }
+fn variant_pattern(variant: &Variant) -> (String, Vec<String>) {
+    let binding = |i: usize, f: &Field| {
+        if f.attrs.skip {
+            "_".to_string()
+        } else {
+            format!("__field{}", i)
+        }
+    };
+    match &variant.fields {
+        Fields::Named(fields) => {
+            let bindings = fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| binding(i, f))
+                .collect::<Vec<_>>();
+            let pattern = fields
+                .iter()
+                .zip(&bindings)
+                .map(|(f, b)| format!("{}: {}", f.ident.as_ref().expect("a named field"), b))
+                .collect::<Vec<_>>();
+            (
+                format!("Self::{} {{ {} }}", variant.ident, pattern.join(", ")),
+                bindings,
+            )
+        }
+        Fields::Unnamed(fields) => {
+            let bindings = fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| binding(i, f))
+                .collect::<Vec<_>>();
+            (
+                format!("Self::{}({})", variant.ident, bindings.join(", ")),
+                bindings,
+            )
+        }
+        Fields::Unit => (format!("Self::{}", variant.ident), vec![]),
+    }
+}
+
+/// フィールドを`Value`に変換する式を返す。`accessors`は各フィールドへの参照
+fn to_fields(fields: &Fields, accessors: &[String]) -> Result<String, String> {
+    check_fields(fields)?;
+    let expr = match fields {
+        Fields::Named(fields) => format!(
+            "{{ let mut object = {MAP}::new(); {statements} {VALUE}::Object(object) }}",
+            statements = to_named_fields(fields, accessors),
+            MAP = MAP,
+            VALUE = VALUE,
+        ),
+        Fields::Unnamed(fields) if fields.len() == 1 => format!(
+            "<{} as {}>::to_value({})",
+            fields[0].ty, TO_VALUE, accessors[0]
+        ),
+        Fields::Unnamed(fields) => {
+            let elements = fields
+                .iter()
+                .zip(accessors)
+                .map(|(f, a)| format!("<{} as {}>::to_value({})", f.ty, TO_VALUE, a))
+                .collect::<Vec<_>>();
+            format!("{}::Array(::std::vec![{}])", VALUE, elements.join(", "))
+        }
+        Fields::Unit => format!("{}::Null", VALUE),
+    };
+    Ok(expr)
+}
+
+/// `object`に名前付きフィールドを挿入する文を返す
+fn to_named_fields(fields: &[Field], accessors: &[String]) -> String {
+    fields
+        .iter()
+        .zip(accessors)
+        .filter(|(f, _)| !f.attrs.skip)
+        .map(|(f, accessor)| {
+            if f.attrs.flatten {
+                format!(
+                    "if let {VALUE}::Object(flatten) = <{ty} as {TO_VALUE}>::to_value({accessor}) {{
+                        object.extend(flatten);
+                    }}",
+                    ty = f.ty,
+                    accessor = accessor,
+                    VALUE = VALUE,
+                    TO_VALUE = TO_VALUE,
+                )
+            } else {
+                format!(
+                    "object.insert(::std::string::String::from({key:?}), <{ty} as {TO_VALUE}>::to_value({accessor}));",
+                    key = field_name(f),
+                    ty = f.ty,
+                    accessor = accessor,
+                    TO_VALUE = TO_VALUE,
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// e.g. "A" or {"B": 1}
+fn to_externally_tagged(
+    name: &str,
+    variant: &Variant,
+    accessors: &[String],
+) -> Result<String, String> {
+    if let Fields::Unit = variant.fields {
+        return Ok(format!(
+            "{}::String(::std::string::String::from({:?}))",
+            VALUE, name
+        ));
+    }
+    Ok(format!(
+        "let mut object = {MAP}::new();
+        object.insert(::std::string::String::from({name:?}), {content});
+        {VALUE}::Object(object)",
+        name = name,
+        content = to_fields(&variant.fields, accessors)?,
+        MAP = MAP,
+        VALUE = VALUE,
+    ))
+}
+
+/// e.g. {"type": "A", "a": 1}
+/// newtype variantの中身は`ToObject`を実装する型に限る(`Object`以外に変換される型はコンパイルエラー)
+fn to_internally_tagged(
+    tag: &str,
+    name: &str,
+    variant: &Variant,
+    accessors: &[String],
+) -> Result<String, String> {
+    check_fields(&variant.fields)?;
+    let content = match &variant.fields {
+        Fields::Unit => String::new(),
+        Fields::Named(fields) => to_named_fields(fields, accessors),
+        Fields::Unnamed(fields) if fields.len() == 1 => format!(
+            "object.extend(<{ty} as {TO_OBJECT}>::to_object({accessor}));",
+            ty = fields[0].ty,
+            accessor = accessors[0],
+            TO_OBJECT = TO_OBJECT,
+        ),
+        Fields::Unnamed(_) => {
+            return Err(format!(
+                "the tuple variant `{}` cannot be internally tagged",
+                variant.ident
+            ))
+        }
+    };
+    Ok(format!(
+        "let mut object = {MAP}::new();
+        object.insert(::std::string::String::from({tag:?}), {VALUE}::String(::std::string::String::from({name:?})));
+        {content}
+        object",
+        tag = tag,
+        name = name,
+        content = content,
+        MAP = MAP,
+        VALUE = VALUE,
+    ))
+}
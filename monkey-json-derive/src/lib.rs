@@ -0,0 +1,83 @@
+//! `monkey_json::FromValue`と`monkey_json::ToValue`を実装するderive macro
+//!
+//! ```
+//! use monkey_json::{from_value, json, to_value};
+//! use monkey_json_derive::{FromJson, ToJson};
+//!
+//! #[derive(Debug, PartialEq, FromJson, ToJson)]
+//! struct User {
+//!     #[json(rename = "userName")]
+//!     name: String,
+//!     #[json(default)]
+//!     age: u32,
+//!     #[json(skip)]
+//!     cache: Option<String>,
+//!     #[json(flatten)]
+//!     role: Role,
+//! }
+//!
+//! #[derive(Debug, PartialEq, FromJson, ToJson)]
+//! #[json(tag = "role")]
+//! enum Role {
+//!     Admin,
+//!     Member { team: String },
+//! }
+//!
+//! let value = json!({"userName": "togatoga", "role": "Member", "team": "json"});
+//! let user: User = from_value(&value).unwrap();
+//! assert_eq!(user.age, 0);
+//! assert_eq!(user.role, Role::Member { team: "json".to_string() });
+//! assert_eq!(
+//!     to_value(&user),
+//!     json!({"userName": "togatoga", "age": 0, "role": "Member", "team": "json"})
+//! );
+//! ```
+//!
+//! 属性
+//! - 型: `#[json(tag = "type")]` `enum`をinternally tagged(`{"type": "A", ...}`)で表現する。
+//!   指定しない場合はexternally tagged(`"A"`もしくは`{"B": ...}`)で表現する
+//! - variant: `#[json(rename = "name")]`
+//! - フィールド: `#[json(rename = "name")]`, `#[json(default)]`, `#[json(default = "path")]`,
+//!   `#[json(skip)]`, `#[json(flatten)]`
+//!
+//! internally taggedの`enum`のnewtype variantの中身は`monkey_json::ToObject`を実装する型
+//! (名前付きフィールドの`struct`、internally taggedの`enum`、`Map`など)に限る。
+//! `Object`以外に変換される型はコンパイルエラーになる
+//!
+//! ```compile_fail
+//! use monkey_json_derive::ToJson;
+//!
+//! #[derive(ToJson)]
+//! #[json(tag = "type")]
+//! enum Id {
+//!     Number(u32),
+//! }
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+
+mod expand;
+mod parse;
+
+/// `monkey_json::FromValue`を実装する
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    derive(input, expand::expand_from_json)
+}
+
+/// `monkey_json::ToValue`を実装する
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    derive(input, expand::expand_to_json)
+}
+
+/// 生成したコードを`TokenStream`に変換する。エラーは`compile_error!`にする
+fn derive(input: TokenStream, expand: fn(&parse::Input) -> Result<String, String>) -> TokenStream {
+    let code = match parse::parse_input(input).and_then(|input| expand(&input)) {
+        Ok(code) => code,
+        Err(msg) => format!("::std::compile_error!({:?});", msg),
+    };
+    code.parse().expect("generated code is valid tokens")
+}
@@ -0,0 +1,518 @@
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// derive対象の型
+pub struct Input {
+    pub name: String,
+    pub generics: Generics,
+    pub attrs: Attrs,
+    pub data: Data,
+}
+
+/// `struct`もしくは`enum`の中身
+pub enum Data {
+    Struct(Fields),
+    Enum(Vec<Variant>),
+}
+
+/// フィールドの一覧
+pub enum Fields {
+    /// e.g. struct A { a: u8 }
+    Named(Vec<Field>),
+    /// e.g. struct A(u8);
+    Unnamed(Vec<Field>),
+    /// e.g. struct A;
+    Unit,
+}
+
+pub struct Field {
+    /// `Fields::Unnamed`の場合は`None`
+    pub ident: Option<String>,
+    pub ty: String,
+    pub attrs: Attrs,
+}
+
+pub struct Variant {
+    pub ident: String,
+    pub fields: Fields,
+    pub attrs: Attrs,
+}
+
+/// `#[json(default = "path")]`の値
+pub enum DefaultValue {
+    /// `Default::default()`を使う
+    Trait,
+    /// 指定した関数を使う
+    Path(String),
+}
+
+/// `#[json(...)]`で指定された属性
+#[derive(Default)]
+pub struct Attrs {
+    pub rename: Option<String>,
+    pub default: Option<DefaultValue>,
+    pub skip: bool,
+    pub flatten: bool,
+    pub tag: Option<String>,
+    /// 指定された属性の名前
+    names: Vec<String>,
+}
+
+impl Attrs {
+    /// `allowed`以外の属性が指定されていればエラーを返す
+    pub fn check(&self, allowed: &[&str], place: &str) -> Result<(), String> {
+        match self
+            .names
+            .iter()
+            .find(|name| !allowed.contains(&name.as_str()))
+        {
+            Some(name) => Err(format!("`#[json({})]` is not supported on {}", name, place)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// ジェネリクスのパラメータ
+enum Param {
+    /// e.g. 'a: 'b
+    Lifetime { name: String, decl: String },
+    /// e.g. T: Clone
+    Type {
+        name: String,
+        bounds: Option<String>,
+    },
+    /// e.g. const N: usize
+    Const { name: String, ty: String },
+}
+
+/// ジェネリクスとwhere句
+#[derive(Default)]
+pub struct Generics {
+    params: Vec<Param>,
+    predicates: Vec<String>,
+}
+
+impl Generics {
+    /// `impl`の後ろに書くパラメータを返す。型パラメータには`bound`を追加する
+    /// e.g. <'a, T: Clone + ::monkey_json::ToValue>
+    pub fn impl_generics(&self, bound: &str) -> String {
+        if self.params.is_empty() {
+            return String::new();
+        }
+        let params = self
+            .params
+            .iter()
+            .map(|param| match param {
+                Param::Lifetime { decl, .. } => decl.clone(),
+                Param::Type {
+                    name,
+                    bounds: Some(bounds),
+                } => format!("{}: {} + {}", name, bounds, bound),
+                Param::Type { name, bounds: None } => format!("{}: {}", name, bound),
+                Param::Const { name, ty } => format!("const {}: {}", name, ty),
+            })
+            .collect::<Vec<_>>();
+        format!("<{}>", params.join(", "))
+    }
+
+    /// 型名の後ろに書くパラメータを返す
+    /// e.g. <'a, T>
+    pub fn ty_generics(&self) -> String {
+        if self.params.is_empty() {
+            return String::new();
+        }
+        let params = self
+            .params
+            .iter()
+            .map(|param| match param {
+                Param::Lifetime { name, .. }
+                | Param::Type { name, .. }
+                | Param::Const { name, .. } => name.clone(),
+            })
+            .collect::<Vec<_>>();
+        format!("<{}>", params.join(", "))
+    }
+
+    /// where句を返す
+    pub fn where_clause(&self) -> String {
+        if self.predicates.is_empty() {
+            String::new()
+        } else {
+            format!("where {}", self.predicates.join(", "))
+        }
+    }
+}
+
+/// `TokenStream`を先頭から読み進める
+struct Cursor {
+    tokens: Vec<TokenTree>,
+    index: usize,
+}
+
+impl Cursor {
+    fn new(tokens: Vec<TokenTree>) -> Cursor {
+        Cursor { tokens, index: 0 }
+    }
+
+    fn peek(&self) -> Option<&TokenTree> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<TokenTree> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn is_empty(&self) -> bool {
+        self.index >= self.tokens.len()
+    }
+
+    /// 次のtokenが`ident`なら読み進めて`true`を返す
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        match self.peek() {
+            Some(TokenTree::Ident(i)) if i.to_string() == ident => {
+                self.index += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 次のtokenが`ch`なら読み進めて`true`を返す
+    fn eat_punct(&mut self, ch: char) -> bool {
+        match self.peek() {
+            Some(TokenTree::Punct(p)) if p.as_char() == ch => {
+                self.index += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<String, String> {
+        match self.next() {
+            Some(TokenTree::Ident(ident)) => Ok(ident.to_string()),
+            _ => Err(format!("{} is expected", what)),
+        }
+    }
+
+    /// `#[...]`を全て読み進め、`#[json(...)]`を解釈して返す
+    fn parse_attrs(&mut self) -> Result<Attrs, String> {
+        let mut attrs = Attrs::default();
+        while let (Some(TokenTree::Punct(p)), Some(TokenTree::Group(g))) =
+            (self.tokens.get(self.index), self.tokens.get(self.index + 1))
+        {
+            if p.as_char() != '#' || g.delimiter() != Delimiter::Bracket {
+                break;
+            }
+            let inner = g.stream().into_iter().collect::<Vec<_>>();
+            if let [TokenTree::Ident(name), TokenTree::Group(args)] = inner.as_slice() {
+                if name.to_string() == "json" && args.delimiter() == Delimiter::Parenthesis {
+                    parse_json_args(args.stream(), &mut attrs)?;
+                }
+            }
+            self.index += 2;
+        }
+        Ok(attrs)
+    }
+
+    /// `pub`, `pub(crate)`などを読み進める
+    fn skip_visibility(&mut self) {
+        if self.eat_ident("pub") {
+            if let Some(TokenTree::Group(g)) = self.peek() {
+                if g.delimiter() == Delimiter::Parenthesis {
+                    self.index += 1;
+                }
+            }
+        }
+    }
+
+    /// `<...>`のジェネリクスを読み進める
+    fn parse_generics(&mut self) -> Result<Generics, String> {
+        let mut generics = Generics::default();
+        if !self.eat_punct('<') {
+            return Ok(generics);
+        }
+        let mut tokens = vec![];
+        let mut depth = 1;
+        let mut arrow = false;
+        loop {
+            let token = self
+                .next()
+                .ok_or_else(|| "unterminated generics".to_string())?;
+            if let TokenTree::Punct(p) = &token {
+                match p.as_char() {
+                    '<' => depth += 1,
+                    '>' if !arrow => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                arrow = p.as_char() == '-' && p.spacing() == Spacing::Joint;
+            } else {
+                arrow = false;
+            }
+            tokens.push(token);
+        }
+        for param in split_commas(tokens) {
+            generics.params.push(parse_param(param)?);
+        }
+        Ok(generics)
+    }
+
+    /// where句を読み進める。`{...}`もしくは`;`の直前で止まる
+    fn parse_where(&mut self, generics: &mut Generics) {
+        if !self.eat_ident("where") {
+            return;
+        }
+        let mut tokens = vec![];
+        while let Some(token) = self.peek() {
+            match token {
+                TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => break,
+                TokenTree::Punct(p) if p.as_char() == ';' => break,
+                _ => tokens.push(self.next().expect("a token exists")),
+            }
+        }
+        generics.predicates = split_commas(tokens).into_iter().map(to_string).collect();
+    }
+}
+
+/// `#[derive]`の入力を解釈する
+pub fn parse_input(input: TokenStream) -> Result<Input, String> {
+    let mut cursor = Cursor::new(input.into_iter().collect());
+    let attrs = cursor.parse_attrs()?;
+    cursor.skip_visibility();
+    let kind = cursor.expect_ident("`struct` or `enum`")?;
+    let name = cursor.expect_ident("a type name")?;
+    let mut generics = cursor.parse_generics()?;
+    cursor.parse_where(&mut generics);
+    let data = match kind.as_str() {
+        "struct" => match cursor.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => {
+                Data::Struct(Fields::Named(parse_named_fields(g.stream())?))
+            }
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                cursor.parse_where(&mut generics);
+                Data::Struct(Fields::Unnamed(parse_unnamed_fields(g.stream())?))
+            }
+            _ => Data::Struct(Fields::Unit),
+        },
+        "enum" => match cursor.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => {
+                Data::Enum(parse_variants(g.stream())?)
+            }
+            _ => return Err("an enum body is expected".to_string()),
+        },
+        _ => return Err("only structs and enums are supported".to_string()),
+    };
+    Ok(Input {
+        name,
+        generics,
+        attrs,
+        data,
+    })
+}
+
+/// e.g. #[json(rename = "a")] pub a: Vec<u8>, b: u8
+fn parse_named_fields(stream: TokenStream) -> Result<Vec<Field>, String> {
+    split_commas(stream.into_iter().collect())
+        .into_iter()
+        .map(|tokens| {
+            let mut cursor = Cursor::new(tokens);
+            let attrs = cursor.parse_attrs()?;
+            cursor.skip_visibility();
+            let ident = cursor.expect_ident("a field name")?;
+            if !cursor.eat_punct(':') {
+                return Err(format!("`:` is expected after the field `{}`", ident));
+            }
+            Ok(Field {
+                ident: Some(ident),
+                ty: to_string(cursor.tokens.split_off(cursor.index)),
+                attrs,
+            })
+        })
+        .collect()
+}
+
+/// e.g. pub u8, Vec<u8>
+fn parse_unnamed_fields(stream: TokenStream) -> Result<Vec<Field>, String> {
+    split_commas(stream.into_iter().collect())
+        .into_iter()
+        .map(|tokens| {
+            let mut cursor = Cursor::new(tokens);
+            let attrs = cursor.parse_attrs()?;
+            cursor.skip_visibility();
+            Ok(Field {
+                ident: None,
+                ty: to_string(cursor.tokens.split_off(cursor.index)),
+                attrs,
+            })
+        })
+        .collect()
+}
+
+/// e.g. A, B(u8), #[json(rename = "c")] C { c: u8 }, D = 3
+fn parse_variants(stream: TokenStream) -> Result<Vec<Variant>, String> {
+    split_commas(stream.into_iter().collect())
+        .into_iter()
+        .map(|tokens| {
+            let mut cursor = Cursor::new(tokens);
+            let attrs = cursor.parse_attrs()?;
+            let ident = cursor.expect_ident("a variant name")?;
+            let fields = match cursor.peek() {
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => {
+                    Fields::Named(parse_named_fields(g.stream())?)
+                }
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                    Fields::Unnamed(parse_unnamed_fields(g.stream())?)
+                }
+                _ => Fields::Unit,
+            };
+            Ok(Variant {
+                ident,
+                fields,
+                attrs,
+            })
+        })
+        .collect()
+}
+
+/// e.g. 'a: 'b, T: Clone = u8, const N: usize
+fn parse_param(tokens: Vec<TokenTree>) -> Result<Param, String> {
+    // デフォルト値は`impl`に書けないので取り除く
+    let tokens = match tokens
+        .iter()
+        .position(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == '='))
+    {
+        Some(i) => tokens[..i].to_vec(),
+        None => tokens,
+    };
+    let mut cursor = Cursor::new(tokens.clone());
+    if cursor.eat_punct('\'') {
+        let name = format!("'{}", cursor.expect_ident("a lifetime")?);
+        return Ok(Param::Lifetime {
+            name,
+            decl: to_string(tokens),
+        });
+    }
+    if cursor.eat_ident("const") {
+        let name = cursor.expect_ident("a const parameter name")?;
+        cursor.eat_punct(':');
+        return Ok(Param::Const {
+            name,
+            ty: to_string(cursor.tokens.split_off(cursor.index)),
+        });
+    }
+    let name = cursor.expect_ident("a type parameter name")?;
+    let bounds = if cursor.eat_punct(':') && !cursor.is_empty() {
+        Some(to_string(cursor.tokens.split_off(cursor.index)))
+    } else {
+        None
+    };
+    Ok(Param::Type { name, bounds })
+}
+
+/// `#[json(...)]`の中身を解釈する
+/// e.g. rename = "a", default
+fn parse_json_args(stream: TokenStream, attrs: &mut Attrs) -> Result<(), String> {
+    for tokens in split_commas(stream.into_iter().collect()) {
+        let mut cursor = Cursor::new(tokens);
+        let name = cursor.expect_ident("an attribute name")?;
+        let value = if cursor.eat_punct('=') {
+            match cursor.next() {
+                Some(TokenTree::Literal(lit)) => Some(unquote(&lit.to_string())?),
+                _ => return Err(format!("a string literal is expected after `{} =`", name)),
+            }
+        } else {
+            None
+        };
+        if !cursor.is_empty() {
+            return Err(format!("unexpected tokens in `#[json({})]`", name));
+        }
+        match (name.as_str(), value) {
+            ("rename", Some(value)) => attrs.rename = Some(value),
+            ("tag", Some(value)) => attrs.tag = Some(value),
+            ("default", Some(path)) => attrs.default = Some(DefaultValue::Path(path)),
+            ("default", None) => attrs.default = Some(DefaultValue::Trait),
+            ("skip", None) => attrs.skip = true,
+            ("flatten", None) => attrs.flatten = true,
+            ("rename", None) | ("tag", None) => {
+                return Err(format!("`#[json({} = \"...\")]` requires a value", name))
+            }
+            ("skip", Some(_)) | ("flatten", Some(_)) => {
+                return Err(format!("`#[json({})]` does not take a value", name))
+            }
+            _ => return Err(format!("unknown attribute `#[json({})]`", name)),
+        }
+        attrs.names.push(name);
+    }
+    Ok(())
+}
+
+/// 文字列リテラルの中身を返す
+/// e.g. "a\"b" => a"b, r#"a"b"# => a"b
+fn unquote(lit: &str) -> Result<String, String> {
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let inner = &raw[hashes..raw.len() - hashes];
+        return Ok(inner[1..inner.len() - 1].to_string());
+    }
+    if lit.len() < 2 || !lit.starts_with('"') || !lit.ends_with('"') {
+        return Err(format!("a string literal is expected, but found {}", lit));
+    }
+    let mut result = String::new();
+    let mut chars = lit[1..lit.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => result.push(c),
+            _ => return Err(format!("unsupported escape in {}", lit)),
+        }
+    }
+    Ok(result)
+}
+
+/// `<...>`の外側にある`,`で分割する
+fn split_commas(tokens: Vec<TokenTree>) -> Vec<Vec<TokenTree>> {
+    let mut result = vec![];
+    let mut current = vec![];
+    let mut depth = 0;
+    let mut arrow = false;
+    for token in tokens {
+        if let TokenTree::Punct(p) = &token {
+            match p.as_char() {
+                ',' if depth == 0 => {
+                    result.push(std::mem::take(&mut current));
+                    arrow = false;
+                    continue;
+                }
+                '<' => depth += 1,
+                // `->`の`>`は数えない
+                '>' if !arrow => depth -= 1,
+                _ => {}
+            }
+            arrow = p.as_char() == '-' && p.spacing() == Spacing::Joint;
+        } else {
+            arrow = false;
+        }
+        current.push(token);
+    }
+    if !current.is_empty() {
+        result.push(current);
+    }
+    result
+}
+
+fn to_string(tokens: Vec<TokenTree>) -> String {
+    tokens.into_iter().collect::<TokenStream>().to_string()
+}
@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+
+use monkey_json::{from_value, json, to_value, FromValue, ToObject, ToValue, Value};
+use monkey_json_derive::{FromJson, ToJson};
+
+fn round_trip<T: FromValue + ToValue + PartialEq + std::fmt::Debug>(value: T, json: Value) {
+    assert_eq!(to_value(&value), json);
+    assert_eq!(from_value::<T>(&json).unwrap(), value);
+}
+
+fn default_port() -> u16 {
+    8080
+}
+
+#[derive(Debug, PartialEq, Default, FromJson, ToJson)]
+struct Meta {
+    created: String,
+}
+
+/// ドキュメントコメントは無視される
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+pub struct Server {
+    /// ホスト名
+    pub host: String,
+    #[json(default = "default_port")]
+    pub(crate) port: u16,
+    #[json(rename = "maxConnections", default)]
+    max_connections: u32,
+    r#type: Option<String>,
+    #[json(skip)]
+    cache: Vec<u8>,
+    #[json(flatten)]
+    meta: Meta,
+    tags: BTreeMap<String, Vec<u8>>,
+}
+
+#[test]
+fn test_struct() {
+    let server = Server {
+        host: "localhost".to_string(),
+        port: 80,
+        max_connections: 10,
+        r#type: None,
+        cache: vec![],
+        meta: Meta {
+            created: "today".to_string(),
+        },
+        tags: BTreeMap::new(),
+    };
+    round_trip(
+        server,
+        json!({
+            "host": "localhost",
+            "port": 80,
+            "maxConnections": 10,
+            "type": null,
+            "created": "today",
+            "tags": {}
+        }),
+    );
+
+    // 省略されたフィールドはデフォルト値、`Option`は`None`になる
+    let value = json!({"host": "h", "created": "c", "tags": {"a": [1]}, "cache": [1]});
+    let server = from_value::<Server>(&value).unwrap();
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.max_connections, 0);
+    assert_eq!(server.r#type, None);
+    assert_eq!(server.cache, Vec::<u8>::new());
+    assert_eq!(server.tags["a"], vec![1]);
+}
+
+#[test]
+fn test_struct_error() {
+    let e = from_value::<Server>(&json!({"created": "c", "tags": {}})).unwrap_err();
+    assert_eq!(e.to_string(), "missing field \"host\"");
+    let e = from_value::<Server>(&json!({"host": "h", "tags": {}})).unwrap_err();
+    assert_eq!(e.to_string(), "missing field \"created\"");
+    let value = json!({"host": "h", "created": "c", "tags": {"a": [1, "2"]}});
+    let e = from_value::<Server>(&value).unwrap_err();
+    assert_eq!(e.to_string(), "/tags/a/1: expected number, found string");
+    let e = from_value::<Server>(&json!([])).unwrap_err();
+    assert_eq!(e.to_string(), "expected object, found array");
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+struct Newtype(u8);
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+struct Pair(String, Option<bool>);
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+struct Unit;
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+struct Wrapper<'a, T: Clone = u8>
+where
+    T: PartialEq,
+{
+    items: Vec<T>,
+    #[json(skip)]
+    name: Option<&'a str>,
+}
+
+#[test]
+fn test_tuple_and_generics() {
+    round_trip(Newtype(1), json!(1));
+    round_trip(Pair("a".to_string(), None), json!(["a", null]));
+    round_trip(Unit, json!(null));
+    round_trip(
+        Wrapper {
+            items: vec![1u8],
+            name: None,
+        },
+        json!({"items": [1]}),
+    );
+    let e = from_value::<Pair>(&json!(["a"])).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "expected an array of length 2, found length 1"
+    );
+    let e = from_value::<Pair>(&json!(["a", 1])).unwrap_err();
+    assert_eq!(e.to_string(), "/1: expected boolean, found number");
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+enum Shape {
+    Empty,
+    #[json(rename = "circle")]
+    Circle(f64),
+    Point(i32, i32),
+    Rect {
+        width: u32,
+        #[json(rename = "h")]
+        height: u32,
+    },
+}
+
+#[test]
+fn test_externally_tagged() {
+    round_trip(Shape::Empty, json!("Empty"));
+    round_trip(Shape::Circle(1.5), json!({"circle": 1.5}));
+    round_trip(Shape::Point(1, -2), json!({"Point": [1, -2]}));
+    round_trip(
+        Shape::Rect {
+            width: 1,
+            height: 2,
+        },
+        json!({"Rect": {"width": 1, "h": 2}}),
+    );
+
+    let e = from_value::<Shape>(&json!("Square")).unwrap_err();
+    assert_eq!(e.to_string(), "unknown variant \"Square\"");
+    let e = from_value::<Shape>(&json!({"Rect": {"width": "1"}})).unwrap_err();
+    assert_eq!(e.to_string(), "/Rect/width: expected number, found string");
+    let e = from_value::<Shape>(&json!({"Rect": {"width": 1}})).unwrap_err();
+    assert_eq!(e.to_string(), "/Rect: missing field \"h\"");
+    let e = from_value::<Shape>(&json!(1)).unwrap_err();
+    assert_eq!(e.to_string(), "expected string or object, found number");
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+#[json(tag = "kind")]
+enum Event {
+    Ping,
+    Message {
+        text: String,
+        #[json(default)]
+        read: bool,
+    },
+    #[json(rename = "meta")]
+    Meta(Meta),
+}
+
+#[test]
+fn test_internally_tagged() {
+    round_trip(Event::Ping, json!({"kind": "Ping"}));
+    round_trip(
+        Event::Message {
+            text: "hi".to_string(),
+            read: true,
+        },
+        json!({"kind": "Message", "text": "hi", "read": true}),
+    );
+    round_trip(
+        Event::Meta(Meta {
+            created: "now".to_string(),
+        }),
+        json!({"kind": "meta", "created": "now"}),
+    );
+    let event = from_value::<Event>(&json!({"text": "hi", "kind": "Message"})).unwrap();
+    assert_eq!(
+        event,
+        Event::Message {
+            text: "hi".to_string(),
+            read: false
+        }
+    );
+
+    let e = from_value::<Event>(&json!({"text": "hi"})).unwrap_err();
+    assert_eq!(e.to_string(), "missing field \"kind\"");
+    let e = from_value::<Event>(&json!({"kind": 1})).unwrap_err();
+    assert_eq!(e.to_string(), "/kind: expected string, found number");
+    let e = from_value::<Event>(&json!({"kind": "Pong"})).unwrap_err();
+    assert_eq!(e.to_string(), "/kind: unknown variant \"Pong\"");
+}
+
+/// newtype variantの中身は`ToObject`を実装する型(`Object`に変換される型)に限る
+#[derive(ToJson)]
+#[json(tag = "type")]
+enum Envelope {
+    Labels(BTreeMap<String, u32>),
+    Nested(Event),
+    Boxed(Box<Meta>),
+}
+
+#[test]
+fn test_internally_tagged_newtype() {
+    let labels = vec![("a".to_string(), 1)].into_iter().collect();
+    assert_eq!(
+        to_value(&Envelope::Labels(labels)),
+        json!({"type": "Labels", "a": 1})
+    );
+    assert_eq!(
+        to_value(&Envelope::Nested(Event::Ping)),
+        json!({"type": "Nested", "kind": "Ping"})
+    );
+    let meta = Meta {
+        created: "now".to_string(),
+    };
+    assert_eq!(Value::Object(meta.to_object()), json!({"created": "now"}));
+    assert_eq!(
+        to_value(&Envelope::Boxed(Box::new(meta))),
+        json!({"type": "Boxed", "created": "now"})
+    );
+}
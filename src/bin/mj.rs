@@ -1,22 +1,43 @@
 use std::{
     env,
-    fs::read_to_string,
-    io::{stdin, Read},
+    fs::{write, File},
+    io::{self, stdin, stdout, Read, Write},
     process::exit,
 };
 
-use monkey_json::Value;
+use monkey_json::{to_string_pretty, JsonLinesReader, ParseOptions, PatchOperation, Value};
 
 fn usage() {
     eprintln!("mj - command line JSON minimum prettier");
     eprintln!("USAGE:");
     eprintln!("      mj [OPTIONS...] [FILE] [OPTIONS...]");
+    eprintln!("      mj diff [OPTIONS...] <FILE1> <FILE2>");
+    eprintln!("      mj merge3 [OPTIONS...] <BASE> <OURS> <THEIRS>");
     eprintln!("ARGS:");
     eprintln!("     <FILE> A JSON file");
     eprintln!("OPTIONS:");
     eprintln!("       -h,--help      Print help information");
     eprintln!("       -c,--color     Color JSON output");
     eprintln!("       -m,--minimize  Minimize JSON output");
+    eprintln!("       -s,--sort-keys Sort object keys (default: keep input order)");
+    eprintln!("       -p,--pointer <POINTER>");
+    eprintln!("                      Print only the value at a JSON pointer (e.g. /a/0/b)");
+    eprintln!("       -l,--lines     Read newline-delimited JSON (NDJSON) and print each record");
+    eprintln!("                      (records without a value at --pointer are skipped)");
+    eprintln!("       --skip-invalid Report invalid lines on stderr and continue (with --lines)");
+    eprintln!("SUBCOMMANDS:");
+    eprintln!("       diff           Print differences between two JSON files");
+    eprintln!("                      (exit status: 0 if same, 1 if different, 2 if trouble)");
+    eprintln!("       merge3         Merge changes in OURS and THEIRS since BASE into OURS");
+    eprintln!("                      (exit status: 0 if merged, 1 if conflicts, 2 if trouble)");
+    eprintln!("DIFF OPTIONS:");
+    eprintln!("       -c,--color     Color diff output");
+    eprintln!("       -m,--minimize  Minimize JSON Patch output");
+    eprintln!("       --patch        Print differences as a JSON Patch (RFC 6902)");
+    eprintln!("       --preserve-order");
+    eprintln!("                      Report changes in object key order (default: ignore)");
+    eprintln!("MERGE3 OPTIONS:");
+    eprintln!("       --stdout       Print the result instead of overwriting OURS");
 }
 
 fn red(s: &str) -> String {
@@ -28,6 +49,22 @@ fn green(s: &str) -> String {
 fn yellow(s: &str) -> String {
     format!("\x1b[33m{}\x1b[m", s)
 }
+/// 文字列をJSONの文字列としてエスケープする(前後の'"'は含まない)
+fn escape(s: &str) -> String {
+    let quoted = monkey_json::to_string(&Value::String(s.to_string()));
+    quoted[1..quoted.len() - 1].to_string()
+}
+/// `Object`のkeyを再帰的に辞書順に並び替える
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Array(vs) => vs.iter_mut().for_each(sort_keys),
+        Value::Object(vs) => {
+            vs.sort_keys();
+            vs.values_mut().for_each(sort_keys);
+        }
+        _ => {}
+    }
+}
 fn do_minimum_output(value: &Value, color: bool) {
     match value {
         Value::Number(v) => {
@@ -37,7 +74,7 @@ fn do_minimum_output(value: &Value, color: bool) {
             print!("{}", v);
         }
         Value::String(s) => {
-            let s = if color { green(s) } else { s.to_string() };
+            let s = if color { green(&escape(s)) } else { escape(s) };
             print!("\"{}\"", s);
         }
         Value::Array(vs) => {
@@ -53,7 +90,7 @@ fn do_minimum_output(value: &Value, color: bool) {
         Value::Object(vs) => {
             print!("{{");
             vs.iter().enumerate().for_each(|(i, (k, v))| {
-                let k = if color { yellow(k) } else { k.to_string() };
+                let k = if color { yellow(&escape(k)) } else { escape(k) };
                 print!("\"{}\":", k);
                 do_minimum_output(v, color);
                 if i != vs.len() - 1 {
@@ -81,7 +118,7 @@ fn do_output(value: &Value, color: bool, indent: usize, special: bool) {
             print!("{}", v)
         }
         Value::String(s) => {
-            let s = if color { green(s) } else { s.to_string() };
+            let s = if color { green(&escape(s)) } else { escape(s) };
             print!("\"{}\"", s);
         }
         Value::Array(vs) => {
@@ -117,7 +154,7 @@ fn do_output(value: &Value, color: bool, indent: usize, special: bool) {
                 println!("{:indent$}{{", "", indent = indent);
             }
             vs.iter().enumerate().for_each(|(i, (k, v))| {
-                let k = if color { yellow(k) } else { k.to_string() };
+                let k = if color { yellow(&escape(k)) } else { escape(k) };
                 print!("{:indent$}\"{}\": ", "", k, indent = indent + 3);
                 match &v {
                     Value::Object(_) | Value::Array(_) => {
@@ -147,17 +184,286 @@ fn do_output(value: &Value, color: bool, indent: usize, special: bool) {
     }
 }
 
+/// `mj`のパースのオプション。数値は入力のリテラルのまま出力する
+fn parse_options() -> ParseOptions {
+    ParseOptions {
+        preserve_number_literals: true,
+        ..ParseOptions::default()
+    }
+}
+
+/// `mj`の入力をパースする
+fn parse_json<R: Read>(input: R) -> Result<Value, monkey_json::Error> {
+    monkey_json::parse_reader_with(input, parse_options())
+}
+
+/// 1つの値を出力する
+fn print_value(value: &Value, minimize: bool, color: bool) {
+    if minimize {
+        do_minimum_output(value, color);
+    } else {
+        do_output(value, color, 0, false);
+    }
+}
+
+/// `mj --lines`の処理。改行区切りのJSONを一行ずつ読み込んで出力する
+/// `skip_invalid`ならパースに失敗した行を標準エラー出力に報告して読み込みを続ける
+fn lines_main<R: Read>(
+    input: R,
+    pointer: Option<&str>,
+    sort_keys_output: bool,
+    minimize_output: bool,
+    color_output: bool,
+    skip_invalid: bool,
+) {
+    for result in JsonLinesReader::with_options(input, parse_options()) {
+        let mut value = match result {
+            Ok((_, value)) => value,
+            // 読み込み自体に失敗した場合は`JsonLinesReader`がそこで終わる
+            Err(e) if skip_invalid => {
+                eprintln!("warning: skipped an invalid line: {}", e);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("error: failed to parse json: {}", e);
+                exit(1);
+            }
+        };
+        if sort_keys_output {
+            sort_keys(&mut value);
+        }
+        // `--pointer`に値がないレコードは出力しない
+        let value = match pointer {
+            Some(pointer) => match value.pointer(pointer) {
+                Some(value) => value,
+                None => continue,
+            },
+            None => &value,
+        };
+        print_value(value, minimize_output, color_output);
+        println!();
+    }
+}
+
+/// ファイルを読み込んでパースする。失敗した場合は終了コード2で終了する
+fn read_json_file(file_name: &str) -> Value {
+    let input = File::open(file_name).unwrap_or_else(|e| {
+        eprintln!("error: can't open a file {}: {}", file_name, e);
+        exit(2);
+    });
+    parse_json(input).unwrap_or_else(|e| {
+        eprintln!("error: failed to parse json {}: {}", file_name, e);
+        exit(2);
+    })
+}
+
+/// `mj diff`の1行を書き出す
+/// e.g. ~ /a/b: 1 -> 2
+fn write_diff_line<W: Write>(out: &mut W, sign: &str, line: String, color: bool) -> io::Result<()> {
+    let line = format!("{} {}", sign, line);
+    let line = match (color, sign) {
+        (false, _) => line,
+        (true, "+") => green(&line),
+        (true, "-") => red(&line),
+        (true, _) => yellow(&line),
+    };
+    writeln!(out, "{}", line)
+}
+
+/// `from`に対する`patch`の各操作を1行ずつ書き出す
+/// パスは各操作を適用した時点の位置を表すので、1つの値に順に適用しながら古い値を取り出す
+fn write_diff<W: Write>(
+    out: &mut W,
+    from: &Value,
+    patch: &[PatchOperation],
+    color: bool,
+) -> io::Result<()> {
+    let mut current = from.clone();
+    for operation in patch {
+        let old = |path: &str| {
+            current
+                .pointer(path)
+                .map_or_else(String::new, |v| v.to_string())
+        };
+        match operation {
+            PatchOperation::Add { path, value } => {
+                write_diff_line(out, "+", format!("{}: {}", path, value), color)?
+            }
+            PatchOperation::Remove { path } => {
+                write_diff_line(out, "-", format!("{}: {}", path, old(path)), color)?
+            }
+            PatchOperation::Replace { path, value } => write_diff_line(
+                out,
+                "~",
+                format!("{}: {} -> {}", path, old(path), value),
+                color,
+            )?,
+            PatchOperation::Move { from, path } => write_diff_line(
+                out,
+                "~",
+                format!("{} -> {}: {} (moved)", from, path, old(from)),
+                color,
+            )?,
+            operation => write_diff_line(
+                out,
+                "~",
+                monkey_json::to_string(&monkey_json::to_value(operation)),
+                color,
+            )?,
+        }
+        operation
+            .apply_to(&mut current)
+            .expect("a patch created by diff can be applied");
+    }
+    Ok(())
+}
+
+/// `mj diff <FILE1> <FILE2>`
+/// 差分がなければ0、あれば1、エラーの場合は2で終了する
+fn diff_main(args: impl Iterator<Item = String>) -> ! {
+    let mut color_output = false;
+    let mut minimize_output = false;
+    let mut patch_output = false;
+    let mut preserve_order = false;
+    let mut file_names = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                usage();
+                exit(0);
+            }
+            "-c" | "--color" => {
+                color_output = true;
+            }
+            "-m" | "--minimize" => {
+                minimize_output = true;
+            }
+            "--patch" => {
+                patch_output = true;
+            }
+            "--preserve-order" => {
+                preserve_order = true;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("error: an unrecognized option {}", arg);
+                usage();
+                exit(2);
+            }
+            _ => file_names.push(arg),
+        }
+    }
+    if file_names.len() != 2 {
+        eprintln!("error: diff requires two files");
+        usage();
+        exit(2);
+    }
+    let values = file_names
+        .iter()
+        .map(|file_name| read_json_file(file_name))
+        .collect::<Vec<_>>();
+
+    let patch = if preserve_order {
+        monkey_json::diff_preserving_order(&values[0], &values[1])
+    } else {
+        monkey_json::diff(&values[0], &values[1])
+    };
+    if patch_output {
+        let patch = monkey_json::to_value(&patch);
+        if minimize_output {
+            do_minimum_output(&patch, color_output);
+        } else {
+            do_output(&patch, color_output, 0, false);
+        }
+        println!();
+    } else if let Err(e) = write_diff(&mut stdout().lock(), &values[0], &patch, color_output) {
+        eprintln!("error: can't write the output: {}", e);
+        exit(2);
+    }
+    exit(if patch.is_empty() { 0 } else { 1 });
+}
+
+/// `mj merge3 <BASE> <OURS> <THEIRS>`
+/// gitのmerge driverとして使えるように、結果を`OURS`に書き込む
+/// 衝突がなければ0、あれば衝突マーカーを書き込んで1、エラーの場合は2で終了する
+fn merge3_main(args: impl Iterator<Item = String>) -> ! {
+    let mut stdout_output = false;
+    let mut file_names = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                usage();
+                exit(0);
+            }
+            "--stdout" => {
+                stdout_output = true;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("error: an unrecognized option {}", arg);
+                usage();
+                exit(2);
+            }
+            _ => file_names.push(arg),
+        }
+    }
+    if file_names.len() != 3 {
+        eprintln!("error: merge3 requires three files");
+        usage();
+        exit(2);
+    }
+    let values = file_names
+        .iter()
+        .map(|file_name| read_json_file(file_name))
+        .collect::<Vec<_>>();
+
+    let (output, code) = match monkey_json::merge3(&values[0], &values[1], &values[2]) {
+        Ok(merged) => (format!("{}\n", to_string_pretty(&merged)), 0),
+        Err(e) => {
+            for conflict in e.conflicts() {
+                eprintln!("conflict: {}", conflict);
+            }
+            // 衝突しなかった変更はマージした上で、ドキュメント全体を衝突マーカーで囲む
+            let output = format!(
+                "<<<<<<< {}\n{}\n=======\n{}\n>>>>>>> {}\n",
+                file_names[1],
+                to_string_pretty(e.ours()),
+                to_string_pretty(e.theirs()),
+                file_names[2]
+            );
+            (output, 1)
+        }
+    };
+    if stdout_output {
+        print!("{}", output);
+    } else if let Err(e) = write(&file_names[1], output) {
+        eprintln!("error: can't write a file {}: {}", file_names[1], e);
+        exit(2);
+    }
+    exit(code);
+}
+
 fn main() {
-    let (args, options): (Vec<String>, Vec<String>) = env::args()
-        .into_iter()
-        .skip(1)
-        .partition(|str| !str.starts_with('-'));
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("diff") => {
+            args.next();
+            diff_main(args);
+        }
+        Some("merge3") => {
+            args.next();
+            merge3_main(args);
+        }
+        _ => {}
+    }
 
     let mut color_output = false;
     let mut minimize_output = false;
-    options
-        .into_iter()
-        .for_each(|option| match option.as_str() {
+    let mut sort_keys_output = false;
+    let mut pointer = None;
+    let mut lines = false;
+    let mut skip_invalid = false;
+    let mut file_name = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
             "-h" | "--help" => {
                 usage();
                 exit(0);
@@ -168,33 +474,122 @@ fn main() {
             "-m" | "--minimize" => {
                 minimize_output = true;
             }
-            _ => {
-                eprintln!("error: an unrecognized option {}", option);
+            "-s" | "--sort-keys" => {
+                sort_keys_output = true;
+            }
+            "-l" | "--lines" => {
+                lines = true;
+            }
+            "--skip-invalid" => {
+                skip_invalid = true;
+            }
+            "-p" | "--pointer" => {
+                let value = args.next().unwrap_or_else(|| {
+                    eprintln!("error: {} requires a JSON pointer", arg);
+                    usage();
+                    exit(1);
+                });
+                pointer = Some(value);
+            }
+            _ if arg.starts_with("--pointer=") => {
+                pointer = Some(arg["--pointer=".len()..].to_string());
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("error: an unrecognized option {}", arg);
                 usage();
                 exit(1);
             }
-        });
-    if args.len() > 1 {
-        eprintln!("error: the number of argument must be 0 or 1");
+            _ => {
+                if file_name.is_some() {
+                    eprintln!("error: the number of argument must be 0 or 1");
+                    usage();
+                    exit(1);
+                }
+                file_name = Some(arg);
+            }
+        }
+    }
+
+    if skip_invalid && !lines {
+        eprintln!("error: --skip-invalid requires --lines");
         usage();
         exit(1);
     }
 
-    let input_json = if let Some(file_name) = args.first() {
-        read_to_string(file_name)
+    // 入力は全体を読み込まずに少しずつパースする
+    let input: Box<dyn Read> = if let Some(file_name) = &file_name {
+        let file = File::open(file_name)
             .ok()
-            .unwrap_or_else(|| panic!("error: can't open a file {}", file_name))
+            .unwrap_or_else(|| panic!("error: can't open a file {}", file_name));
+        Box::new(file)
     } else {
-        let mut buffer = String::new();
-        stdin()
-            .read_to_string(&mut buffer)
-            .expect("error: can't read a string from stdin");
-        buffer
+        Box::new(stdin().lock())
     };
-    let json_value = monkey_json::parse(&input_json).expect("error: failed to parse json");
-    if minimize_output {
-        do_minimum_output(&json_value, color_output);
-    } else {
-        do_output(&json_value, color_output, 0, false);
+    if lines {
+        lines_main(
+            input,
+            pointer.as_deref(),
+            sort_keys_output,
+            minimize_output,
+            color_output,
+            skip_invalid,
+        );
+        return;
+    }
+    let parsed = parse_json(input);
+    let mut json_value = parsed.unwrap_or_else(|e| {
+        eprintln!("error: failed to parse json: {}", e);
+        exit(1);
+    });
+    if sort_keys_output {
+        sort_keys(&mut json_value);
+    }
+    // `--pointer`が指定された場合はその部分だけを出力する
+    let json_value = match &pointer {
+        Some(pointer) => json_value.pointer(pointer).unwrap_or_else(|| {
+            eprintln!("error: no value at the JSON pointer {:?}", pointer);
+            exit(1);
+        }),
+        None => &json_value,
+    };
+    print_value(json_value, minimize_output, color_output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use monkey_json::json;
+
+    fn diff_lines(from: &Value, to: &Value, color: bool) -> String {
+        let mut out = vec![];
+        write_diff(&mut out, from, &monkey_json::diff(from, to), color).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_diff() {
+        assert_eq!(diff_lines(&json!({"a": 1}), &json!({"a": 1}), false), "");
+        // 古い値は前の操作を適用した後の位置から取り出す
+        assert_eq!(
+            diff_lines(
+                &json!({"a": [1, 2, 3], "b": {"c": null}}),
+                &json!({"a": [0, 1, 3, 4], "b": {"c": "x"}}),
+                false
+            ),
+            "- /a/1: 2\n+ /a/0: 0\n+ /a/3: 4\n~ /b/c: null -> \"x\"\n"
+        );
+        assert_eq!(
+            diff_lines(
+                &json!(["a", "b", "c", "d"]),
+                &json!(["d", "a", "b", "c"]),
+                false
+            ),
+            "~ /3 -> /0: \"d\" (moved)\n"
+        );
+        // 色は操作の種類ごとに付ける
+        assert_eq!(
+            diff_lines(&json!({"a": 1, "b": 2}), &json!({"a": 3, "c": 4}), true),
+            "\x1b[31m- /b: 2\x1b[m\n\x1b[33m~ /a: 1 -> 3\x1b[m\n\x1b[32m+ /c: 4\x1b[m\n"
+        );
     }
 }
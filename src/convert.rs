@@ -0,0 +1,176 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    hash::BuildHasher,
+    iter::FromIterator,
+};
+
+use crate::{Map, Number, Value};
+
+macro_rules! impl_from_number {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    Value::Number(n.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_number!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+/// `()`は`Null`に変換する
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Null
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Value {
+    fn from(s: Cow<'a, str>) -> Self {
+        Value::String(s.into_owned())
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Self {
+        Value::String(c.to_string())
+    }
+}
+
+/// `None`は`Null`に変換する
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        match v {
+            Some(v) => v.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(v: &[T]) -> Self {
+        Value::Array(v.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl From<Map> for Value {
+    fn from(map: Map) -> Self {
+        Value::Object(map)
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+/// `HashMap`は順番を持たないので、keyの順番は不定になる
+impl<K: Into<String>, V: Into<Value>, S: BuildHasher> From<HashMap<K, V, S>> for Value {
+    fn from(map: HashMap<K, V, S>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+/// `Array`として集める
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Value::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// `Object`として集める
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Object(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_from() {
+        assert_eq!(Value::from(1u8), Value::Number(1.into()));
+        assert_eq!(Value::from(-1i64), Value::Number((-1).into()));
+        assert_eq!(Value::from(0.5), Value::Number(0.5.into()));
+        assert_eq!(Value::from(true), Value::Bool(true));
+        assert_eq!(Value::from(()), Value::Null);
+        assert_eq!(Value::from("a"), Value::String("a".to_string()));
+        assert_eq!(Value::from('a'), Value::String("a".to_string()));
+        assert_eq!(
+            Value::from(Cow::Borrowed("a")),
+            Value::from("a".to_string())
+        );
+        assert_eq!(Value::from(None::<i32>), Value::Null);
+        assert_eq!(Value::from(Some(1)), Value::from(1));
+        assert_eq!(
+            Value::from(vec![Some(1), None]),
+            parse("[1, null]").unwrap()
+        );
+        assert_eq!(
+            Value::from(&["a", "b"][..]),
+            parse(r#"["a", "b"]"#).unwrap()
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert("b", vec![1]);
+        map.insert("a", vec![]);
+        let value = Value::from(map);
+        assert_eq!(value, parse(r#"{"a": [], "b": [1]}"#).unwrap());
+        // `BTreeMap`はkeyの順番に並ぶ
+        let keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["a", "b"]);
+
+        let mut map = HashMap::new();
+        map.insert("a".to_string(), 1);
+        assert_eq!(Value::from(map), parse(r#"{"a": 1}"#).unwrap());
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let value = (1..=3).collect::<Value>();
+        assert_eq!(value, parse("[1, 2, 3]").unwrap());
+        let value = vec![("x", 1), ("y", 2)].into_iter().collect::<Value>();
+        assert_eq!(value, parse(r#"{"x": 1, "y": 2}"#).unwrap());
+    }
+}
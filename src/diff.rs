@@ -0,0 +1,576 @@
+use crate::{pointer::escape_token, Map, PatchOperation, Value};
+
+/// `diff_array`で探索する編集距離(削除と追加の数)の上限
+/// 超える場合は共通の先頭と末尾以外を位置ごとに比較する
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// `from`に適用すると`to`になるJSON Patch(RFC 6902)を返す
+/// - `Object`はkeyごとに再帰的に比較する。keyの順序は比較しない
+/// - `Array`はLCS(最長共通部分列)で比較し、移動した要素は`move`で表す
+/// - それ以外は値が異なれば`replace`する
+pub fn diff(from: &Value, to: &Value) -> Vec<PatchOperation> {
+    let mut operations = vec![];
+    diff_value(&mut operations, "", from, to, false);
+    operations
+}
+
+/// `diff`と同じだが、`Object`のkeyの順序も`to`と同じになるJSON Patchを返す
+/// 順序が異なるkeyは一時的なkeyを経由する`move`で末尾に移してから、値を再帰的に比較する
+/// (JSON Patchはkeyの順序を持たないため、順序を保つ実装で適用した場合にだけ順序が一致する)
+pub fn diff_preserving_order(from: &Value, to: &Value) -> Vec<PatchOperation> {
+    let mut operations = vec![];
+    diff_value(&mut operations, "", from, to, true);
+    operations
+}
+
+/// 値が等しいかどうかを返す。`preserve_order`なら`Object`のkeyの順序も比較する
+fn same(from: &Value, to: &Value, preserve_order: bool) -> bool {
+    if !preserve_order {
+        return from == to;
+    }
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => {
+            from.len() == to.len()
+                && from
+                    .iter()
+                    .zip(to)
+                    .all(|((k1, v1), (k2, v2))| k1 == k2 && same(v1, v2, true))
+        }
+        (Value::Array(from), Value::Array(to)) => {
+            from.len() == to.len() && from.iter().zip(to).all(|(a, b)| same(a, b, true))
+        }
+        _ => from == to,
+    }
+}
+
+fn diff_value(
+    operations: &mut Vec<PatchOperation>,
+    path: &str,
+    from: &Value,
+    to: &Value,
+    preserve_order: bool,
+) {
+    if same(from, to, preserve_order) {
+        return;
+    }
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => {
+            diff_object(operations, path, from, to, preserve_order)
+        }
+        (Value::Array(from), Value::Array(to)) => {
+            diff_array(operations, path, from, to, preserve_order)
+        }
+        _ => operations.push(PatchOperation::Replace {
+            path: path.to_string(),
+            value: to.clone(),
+        }),
+    }
+}
+
+fn diff_object(
+    operations: &mut Vec<PatchOperation>,
+    path: &str,
+    from: &Map,
+    to: &Map,
+    preserve_order: bool,
+) {
+    for key in from.keys() {
+        if !to.contains_key(key) {
+            operations.push(PatchOperation::Remove {
+                path: format!("{}/{}", path, escape_token(key)),
+            });
+        }
+    }
+    if !preserve_order {
+        for (key, value) in to {
+            let path = format!("{}/{}", path, escape_token(key));
+            match from.get(key) {
+                Some(old) => diff_value(operations, &path, old, value, false),
+                None => operations.push(PatchOperation::Add {
+                    path,
+                    value: value.clone(),
+                }),
+            }
+        }
+        return;
+    }
+    // `to`の先頭から、残ったkeyの中で同じ順序に並んでいる間はその場で比較する
+    // それ以降のkeyは末尾に移し直す(追加したkeyは末尾に並ぶため)
+    let mut kept = from.keys().filter(|key| to.contains_key(key));
+    let mut in_order = true;
+    for (key, value) in to {
+        let key_path = format!("{}/{}", path, escape_token(key));
+        let old = from.get(key);
+        if in_order {
+            // 飛ばしたkeyは`to`でより後ろにあるので、後で末尾に移される
+            in_order = old.is_some() && kept.any(|k| k == key);
+        }
+        match old {
+            Some(old) => {
+                if !in_order {
+                    // 同じpathへの`move`は何もしないので、使われていないkeyを経由して末尾に移す
+                    let mut temporary = format!("{}~", key);
+                    while from.contains_key(&temporary) || to.contains_key(&temporary) {
+                        temporary.push('~');
+                    }
+                    let temporary = format!("{}/{}", path, escape_token(&temporary));
+                    operations.push(PatchOperation::Move {
+                        from: key_path.clone(),
+                        path: temporary.clone(),
+                    });
+                    operations.push(PatchOperation::Move {
+                        from: temporary,
+                        path: key_path.clone(),
+                    });
+                }
+                diff_value(operations, &key_path, old, value, true);
+            }
+            None => operations.push(PatchOperation::Add {
+                path: key_path,
+                value: value.clone(),
+            }),
+        }
+    }
+}
+
+/// `to`の各要素を`from`からどう作るか
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    /// `from[a]`をそのまま使う(LCSに含まれる)
+    Keep(usize),
+    /// `from[a]`を再帰的に変更する
+    Change(usize),
+    /// `from[a]`を移動する
+    Move(usize),
+    /// 新しく追加する
+    Insert,
+}
+
+/// 位置ごとの要素数を持ち、先頭からの合計を`O(log n)`で返す(Fenwick tree)
+struct Counts {
+    tree: Vec<isize>,
+}
+
+impl Counts {
+    fn new(len: usize) -> Counts {
+        Counts {
+            tree: vec![0; len + 1],
+        }
+    }
+
+    /// `i`番目の要素数に`delta`を足す
+    fn add(&mut self, i: usize, delta: isize) {
+        let mut i = i + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// `..end`番目の要素数の合計を返す
+    fn sum(&self, end: usize) -> usize {
+        let (mut i, mut sum) = (end, 0);
+        while i > 0 {
+            sum += self.tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum as usize
+    }
+}
+
+fn diff_array(
+    operations: &mut Vec<PatchOperation>,
+    path: &str,
+    from: &[Value],
+    to: &[Value],
+    preserve_order: bool,
+) {
+    let lcs = lcs(from, to, preserve_order);
+    let mut edits = vec![None; to.len()];
+    let mut deleted = vec![true; from.len()];
+    for &(a, b) in &lcs {
+        edits[b] = Some(Edit::Keep(a));
+        deleted[a] = false;
+    }
+    // LCSに含まれない要素のうち、等しいものは移動として扱う
+    // `lcs`が位置ごとの比較に切り替えた場合は、比較の回数が多くなるため探さない
+    if from.len() + to.len() - 2 * lcs.len() <= MAX_EDIT_DISTANCE {
+        let mut removed = (0..from.len()).filter(|&a| deleted[a]).collect::<Vec<_>>();
+        for (b, edit) in edits.iter_mut().enumerate() {
+            if edit.is_none() {
+                if let Some(i) = removed
+                    .iter()
+                    .position(|&a| same(&from[a], &to[b], preserve_order))
+                {
+                    let a = removed.remove(i);
+                    *edit = Some(Edit::Move(a));
+                    deleted[a] = false;
+                }
+            }
+        }
+    }
+    // LCSの要素の間で削除と追加が対応するものは変更として扱う
+    let mut bounds = lcs.clone();
+    bounds.push((from.len(), to.len()));
+    let (mut a_start, mut b_start) = (0, 0);
+    for (a_end, b_end) in bounds {
+        let mut removed = (a_start..a_end)
+            .filter(|&a| deleted[a])
+            .collect::<Vec<_>>()
+            .into_iter();
+        for edit in edits[b_start..b_end].iter_mut().filter(|e| e.is_none()) {
+            match removed.next() {
+                Some(a) => {
+                    *edit = Some(Edit::Change(a));
+                    deleted[a] = false;
+                }
+                None => break,
+            }
+        }
+        a_start = a_end + 1;
+        b_start = b_end + 1;
+    }
+
+    // 削除は添字がずれないよう後ろから行う
+    for a in (0..from.len()).rev().filter(|&a| deleted[a]) {
+        operations.push(PatchOperation::Remove {
+            path: format!("{}/{}", path, a),
+        });
+    }
+    // 作業中の`Array`の並びを位置(slot)ごとの要素数で表す
+    // slot `2a+1`は`from[a]`、slot `2a+2`は`from[a]`の直後に置いた要素、slot 0は先頭に置いた要素
+    let mut counts = Counts::new(2 * from.len() + 1);
+    for a in (0..from.len()).filter(|&a| !deleted[a]) {
+        counts.add(2 * a + 1, 1);
+    }
+    // 最後に確定した要素のslot。確定した要素は`to`の順に並ぶため、次の要素はその直後に置く
+    let mut cursor = 0;
+    for (b, edit) in edits.into_iter().enumerate() {
+        // 要素を置くslotと、作業中の`Array`での添字
+        let slot = cursor + cursor % 2;
+        match edit.unwrap_or(Edit::Insert) {
+            Edit::Keep(a) => cursor = 2 * a + 1,
+            Edit::Change(a) => {
+                let i = counts.sum(2 * a + 1);
+                diff_value(
+                    operations,
+                    &format!("{}/{}", path, i),
+                    &from[a],
+                    &to[b],
+                    preserve_order,
+                );
+                cursor = 2 * a + 1;
+            }
+            Edit::Move(a) => {
+                let i = counts.sum(2 * a + 1);
+                counts.add(2 * a + 1, -1);
+                // 取り除いた後の位置に挿入する
+                let next = counts.sum(slot + 1);
+                if i != next {
+                    operations.push(PatchOperation::Move {
+                        from: format!("{}/{}", path, i),
+                        path: format!("{}/{}", path, next),
+                    });
+                }
+                counts.add(slot, 1);
+                cursor = slot;
+            }
+            Edit::Insert => {
+                operations.push(PatchOperation::Add {
+                    path: format!("{}/{}", path, counts.sum(slot + 1)),
+                    value: to[b].clone(),
+                });
+                counts.add(slot, 1);
+                cursor = slot;
+            }
+        }
+    }
+}
+
+/// 最長共通部分列を`(fromの添字, toの添字)`の昇順で返す
+/// 共通の先頭と末尾を除いた部分の編集距離が`MAX_EDIT_DISTANCE`を超える場合は、共通の先頭と末尾だけを返す
+fn lcs(from: &[Value], to: &[Value], preserve_order: bool) -> Vec<(usize, usize)> {
+    // 共通の先頭と末尾はそのまま対応させる
+    let prefix = from
+        .iter()
+        .zip(to)
+        .take_while(|(a, b)| same(a, b, preserve_order))
+        .count();
+    let suffix = from[prefix..]
+        .iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b, preserve_order))
+        .count();
+    let (from_mid, to_mid) = (
+        &from[prefix..from.len() - suffix],
+        &to[prefix..to.len() - suffix],
+    );
+    let mut result = (0..prefix).map(|i| (i, i)).collect::<Vec<_>>();
+    result.extend(
+        myers(from_mid, to_mid, preserve_order)
+            .into_iter()
+            .map(|(i, j)| (prefix + i, prefix + j)),
+    );
+    result.extend((0..suffix).map(|k| (from.len() - suffix + k, to.len() - suffix + k)));
+    result
+}
+
+/// Myersの差分アルゴリズムで最長共通部分列を返す。編集距離を`d`として`O((n + m) * d)`で求める
+/// 編集距離が`MAX_EDIT_DISTANCE`を超える場合は空を返す
+/// http://www.xmailserver.org/diff2.pdf
+fn myers(from: &[Value], to: &[Value], preserve_order: bool) -> Vec<(usize, usize)> {
+    let (n, m) = (from.len() as isize, to.len() as isize);
+    let max = (n + m).min(MAX_EDIT_DISTANCE as isize);
+    // trace[d][k + d]は、d回の削除と追加で到達できる対角線`k = x - y`上の最も遠い`x`
+    let mut trace: Vec<Vec<isize>> = vec![];
+    for d in 0..=max {
+        let mut v = vec![0; 2 * d as usize + 1];
+        for k in (-d..=d).step_by(2) {
+            let mut x = match trace.last() {
+                None => 0,
+                Some(prev) => {
+                    let prev = |k: isize| prev[(k + d - 1) as usize];
+                    // 上の対角線から下に進む(追加)か、下の対角線から右に進む(削除)
+                    if k == -d || (k != d && prev(k - 1) < prev(k + 1)) {
+                        prev(k + 1)
+                    } else {
+                        prev(k - 1) + 1
+                    }
+                }
+            };
+            let mut y = x - k;
+            while x < n && y < m && same(&from[x as usize], &to[y as usize], preserve_order) {
+                x += 1;
+                y += 1;
+            }
+            v[(k + d) as usize] = x;
+            if x >= n && y >= m {
+                trace.push(v);
+                return backtrack(&trace, n, m);
+            }
+        }
+        trace.push(v);
+    }
+    vec![]
+}
+
+/// `myers`の探索結果を`(n, m)`から遡り、斜めに進んだ位置を最長共通部分列として返す
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<(usize, usize)> {
+    let mut result = vec![];
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let prev = |k: isize| trace[d as usize - 1][(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && prev(k - 1) < prev(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let (prev_x, prev_y) = (prev(prev_k), prev(prev_k) - prev_k);
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            result.push((x as usize, y as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        result.push((x as usize, y as usize));
+    }
+    result.reverse();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, test_util::Rng, to_string, to_value};
+
+    /// keyを再帰的に並び替えて出力する
+    fn sorted(value: &Value) -> String {
+        fn sort(value: &mut Value) {
+            match value {
+                Value::Array(array) => array.iter_mut().for_each(sort),
+                Value::Object(map) => {
+                    map.sort_keys();
+                    map.values_mut().for_each(sort);
+                }
+                _ => {}
+            }
+        }
+        let mut value = value.clone();
+        sort(&mut value);
+        to_string(&value)
+    }
+
+    /// `from`に`patch`を適用した結果が`to`と同じ出力になることを確かめる
+    /// `preserve_order`でなければkeyの順序は比較しない
+    fn assert_patched(from: &Value, to: &Value, patch: &[PatchOperation], preserve_order: bool) {
+        let mut patched = from.clone();
+        patched.apply_patch(patch).unwrap();
+        if preserve_order {
+            assert_eq!(to_string(&patched), to_string(to), "{} => {}", from, to);
+        } else {
+            assert_eq!(sorted(&patched), sorted(to), "{} => {}", from, to);
+        }
+    }
+
+    fn assert_diff(from: Value, to: Value, expected: Value) {
+        let patch = diff(&from, &to);
+        assert_eq!(to_value(&patch), expected, "{} => {}", from, to);
+        assert_patched(&from, &to, &patch, false);
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_diff(json!({"a": 1}), json!({"a": 1}), json!([]));
+        assert_diff(
+            json!(1),
+            json!("1"),
+            json!([{"op": "replace", "path": "", "value": "1"}]),
+        );
+        assert_diff(
+            json!({"a": 1, "b": {"c": [1], "d~/": true}}),
+            json!({"b": {"c": [1], "d~/": false}, "e": null}),
+            json!([
+                {"op": "remove", "path": "/a"},
+                {"op": "replace", "path": "/b/d~0~1", "value": false},
+                {"op": "add", "path": "/e", "value": null}
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_diff_array() {
+        assert_diff(
+            json!([1, 2, 3]),
+            json!([0, 1, 3, 4]),
+            json!([
+                {"op": "remove", "path": "/1"},
+                {"op": "add", "path": "/0", "value": 0},
+                {"op": "add", "path": "/3", "value": 4}
+            ]),
+        );
+        // 移動した要素は`move`になる
+        assert_diff(
+            json!(["a", "b", "c", "d"]),
+            json!(["d", "a", "b", "c"]),
+            json!([{"op": "move", "from": "/3", "path": "/0"}]),
+        );
+        assert_diff(
+            json!(["a", "b", "c", "d"]),
+            json!(["b", "c", "d", "a"]),
+            json!([{"op": "move", "from": "/0", "path": "/3"}]),
+        );
+        // 対応する要素は再帰的に比較する
+        assert_diff(
+            json!([{"id": 1, "v": "x"}, {"id": 2}]),
+            json!([{"id": 1, "v": "y"}, {"id": 2}, {"id": 3}]),
+            json!([
+                {"op": "replace", "path": "/0/v", "value": "y"},
+                {"op": "add", "path": "/2", "value": {"id": 3}}
+            ]),
+        );
+        assert_diff(
+            json!([1, 2, 3, 4, 5]),
+            json!([1, 5]),
+            json!([
+                {"op": "remove", "path": "/3"},
+                {"op": "remove", "path": "/2"},
+                {"op": "remove", "path": "/1"}
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_diff_key_order() {
+        // `diff`はkeyの順序を比較しない
+        let from = json!({"a": 1, "b": {"x": 1, "y": 2}, "c": 3});
+        let to = json!({"a": 1, "c": 3, "b": {"x": 1, "y": 5}});
+        assert_diff(
+            from.clone(),
+            to.clone(),
+            json!([{"op": "replace", "path": "/b/y", "value": 5}]),
+        );
+        // `diff_preserving_order`は順序が異なるkeyを末尾に移してから再帰的に比較する
+        let patch = diff_preserving_order(&from, &to);
+        assert_eq!(
+            to_value(&patch),
+            json!([
+                {"op": "move", "from": "/b", "path": "/b~0"},
+                {"op": "move", "from": "/b~0", "path": "/b"},
+                {"op": "replace", "path": "/b/y", "value": 5}
+            ])
+        );
+        assert_patched(&from, &to, &patch, true);
+        // 一時的なkeyは使われていないものを選ぶ
+        let patch = diff_preserving_order(
+            &json!({"a": 1, "a~": 2, "b": 3}),
+            &json!({"b": 3, "a": 1, "a~": 2}),
+        );
+        assert_eq!(
+            patch[0],
+            PatchOperation::Move {
+                from: "/a".to_string(),
+                path: "/a~0~0".to_string(),
+            }
+        );
+        assert_eq!(diff_preserving_order(&from, &from), vec![]);
+    }
+
+    #[test]
+    fn test_diff_large_array() {
+        // 長い`Array`の一部だけを変更した場合
+        let from = (0..100_000).map(|i| json!(i)).collect::<Value>();
+        let mut to = from.clone();
+        to[50_000] = json!("x");
+        to.pointer_insert("/70000", json!(null)).unwrap();
+        to.pointer_remove("/10").unwrap();
+        assert_diff(
+            from.clone(),
+            to,
+            json!([
+                {"op": "remove", "path": "/10"},
+                {"op": "replace", "path": "/49999", "value": "x"},
+                {"op": "add", "path": "/69999", "value": null}
+            ]),
+        );
+        // 編集距離が上限を超える場合は位置ごとに比較する
+        let to = (0..100_000).map(|i| json!(-i)).collect::<Value>();
+        let patch = diff(&from, &to);
+        assert_eq!(patch.len(), 99_999);
+        assert_patched(&from, &to, &patch, false);
+    }
+
+    /// 衝突しやすいように少ない種類の値から生成する
+    fn random_value(rng: &mut Rng, depth: usize) -> Value {
+        let kinds = if depth == 0 { 3 } else { 5 };
+        match rng.below(kinds) {
+            0 => Value::Null,
+            1 => json!(rng.below(4)),
+            2 => json!(["a", "b", "c"][rng.below(3) as usize]),
+            3 => (0..rng.below(6))
+                .map(|_| random_value(rng, depth - 1))
+                .collect(),
+            _ => (0..rng.below(4))
+                .map(|_| (rng.below(4).to_string(), random_value(rng, depth - 1)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_round_trip() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..1000 {
+            let from = random_value(&mut rng, 3);
+            let to = random_value(&mut rng, 3);
+            assert_patched(&from, &to, &diff(&from, &to), false);
+            assert_patched(&from, &to, &diff_preserving_order(&from, &to), true);
+            assert_eq!(diff(&from, &from), vec![]);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+
+use crate::Position;
+
+/// エラーの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// JSONとして予期しない文字
+    /// e.g. `@`, `nul`
+    UnexpectedChar,
+    /// 不正なエスケープ文字列
+    /// e.g. `"\x"`, `"\u12"`
+    InvalidEscape,
+    /// UTF-16のサロゲートペアとして不正な文字列
+    /// e.g. `"\uD83D"`
+    InvalidUnicode,
+    /// 文字列中のエスケープされていない制御文字
+    ControlCharacter,
+    /// 数値として不正な文字列
+    /// e.g. `1e`, `--1`
+    InvalidNumber,
+    /// JSONの文法として予期しない`Token`
+    /// e.g. `[1 2]`
+    UnexpectedToken,
+    /// 入力が途中で終わっている
+    /// e.g. `[1,`
+    UnexpectedEof,
+    /// 値の後ろに余計なデータがある
+    /// e.g. `[1] garbage`
+    TrailingData,
+    /// ネストが深すぎる
+    DepthExceeded,
+    /// `Object`のkeyが重複している(`DuplicateKeys::Error`の場合)
+    /// e.g. `{"a": 1, "a": 2}`
+    DuplicateKey,
+    /// 入力の読み込みに失敗した
+    Io,
+    /// 入力が不正なUTF-8のバイト列を含む
+    /// e.g. `b"[\"\xff\"]"`
+    InvalidUtf8,
+}
+
+/// `ErrorKind::InvalidUtf8`のエラーメッセージ
+pub(crate) const INVALID_UTF8: &str = "the input is not valid UTF-8";
+
+/// パース中に発生したエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    msg: String,
+    position: Position,
+    related_position: Option<Position>,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, msg: &str, position: Position) -> Error {
+        Error {
+            kind,
+            msg: msg.to_string(),
+            position,
+            related_position: None,
+        }
+    }
+
+    /// エラーに関連する別の位置を設定する
+    pub(crate) fn with_related_position(mut self, position: Position) -> Error {
+        self.related_position = Some(position);
+        self
+    }
+
+    /// エラーの種類を返す
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// エラーが発生した位置を返す
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// エラーに関連する別の位置を返す
+    /// e.g. `ErrorKind::DuplicateKey`の場合は最初に出現したkeyの位置
+    pub fn related_position(&self) -> Option<Position> {
+        self.related_position
+    }
+
+    /// 位置を含まないエラーメッセージを返す
+    /// e.g. ']' or ',' is expected, but found number 3
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// e.g. ']' or ',' is expected, but found number 3 at line 2, column 4 (byte 13)
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.msg, self.position)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_with, DuplicateKeys, ParseOptions};
+
+    #[test]
+    fn test_error_kind() {
+        let strict = ParseOptions::strict();
+        let cases = [
+            ("[1, @]", ErrorKind::UnexpectedChar),
+            ("[nul]", ErrorKind::UnexpectedChar),
+            (r#"["\x"]"#, ErrorKind::InvalidEscape),
+            (r#"["\u12"]"#, ErrorKind::InvalidEscape),
+            (r#"["\uD83D"]"#, ErrorKind::InvalidUnicode),
+            ("[\"a\u{01}\"]", ErrorKind::ControlCharacter),
+            ("[1e]", ErrorKind::InvalidNumber),
+            ("[1 2]", ErrorKind::UnexpectedToken),
+            ("{1: 2}", ErrorKind::UnexpectedToken),
+            ("[1,", ErrorKind::UnexpectedEof),
+            ("\"abc", ErrorKind::UnexpectedEof),
+            ("", ErrorKind::UnexpectedEof),
+            ("[1] 2", ErrorKind::TrailingData),
+        ];
+        for (input, kind) in cases {
+            let e = parse_with(input, strict).unwrap_err();
+            assert_eq!(e.kind(), kind, "{}: {}", input, e);
+        }
+    }
+
+    #[test]
+    fn test_depth_exceeded() {
+        let input = "[".repeat(200) + &"]".repeat(200);
+        // デフォルトでは上限を設けない
+        assert!(parse(&input).is_ok());
+
+        let options = ParseOptions {
+            max_depth: Some(crate::DEFAULT_MAX_DEPTH),
+            ..ParseOptions::default()
+        };
+        let e = parse_with(&input, options).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::DepthExceeded);
+        assert_eq!(e.position().offset, 128);
+    }
+
+    #[test]
+    fn test_display() {
+        let e = parse("{\"a\": [1,\n 2 3]}").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "']' or ',' is expected, but found number 3 at line 2, column 4 (byte 13)"
+        );
+        // `Box<dyn std::error::Error>`に変換できる
+        let e: Box<dyn std::error::Error> = Box::new(e);
+        assert!(e.to_string().starts_with("']' or ','"));
+    }
+
+    #[test]
+    fn test_duplicate_key() {
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::default()
+        };
+        let input = "{\"a\": 1,\n \"b\": {\"a\": 2}, \"a\": 3}";
+        let e = parse_with(input, options).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(e.position().offset, 25);
+        assert_eq!(e.position().line, 2);
+        assert_eq!(e.related_position().map(|p| p.offset), Some(1));
+        // ネストした`Object`のkeyは別々に扱う
+        assert!(parse_with(r#"{"a": {"a": 1}}"#, options).is_ok());
+    }
+}
@@ -0,0 +1,483 @@
+use std::io::{BufReader, Read};
+
+use crate::{
+    lexer::{Lexer, Position, Span, Token},
+    source::Source,
+    Error, ErrorKind, Mode, Number, ParseOptions,
+};
+
+/// `EventReader`が読み込む`Token`の入力元
+pub trait TokenSource {
+    /// 次の`Token`とその範囲を返す。入力の終端では`None`を返す
+    fn next_token(&mut self) -> Result<Option<(Token, Span)>, Error>;
+
+    /// 入力の終端の位置を返す。`next_token`が`None`を返した後に呼ばれる
+    fn end(&self) -> Position;
+}
+
+/// 空白以外の`Token`を`Lexer`から一つずつ読み込む
+impl<S: Source> TokenSource for Lexer<S> {
+    fn next_token(&mut self) -> Result<Option<(Token, Span)>, Error> {
+        loop {
+            match Lexer::next_token(self)? {
+                Some((Token::WhiteSpace, _)) => continue,
+                token => return Ok(token),
+            }
+        }
+    }
+
+    fn end(&self) -> Position {
+        self.position()
+    }
+}
+
+impl<T: TokenSource + ?Sized> TokenSource for &mut T {
+    fn next_token(&mut self) -> Result<Option<(Token, Span)>, Error> {
+        (**self).next_token()
+    }
+
+    fn end(&self) -> Position {
+        (**self).end()
+    }
+}
+
+/// `EventReader`が返すイベント
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `Object`の開始 `{`
+    StartObject,
+    /// `Object`の終了 `}`
+    EndObject,
+    /// `Array`の開始 `[`
+    StartArray,
+    /// `Array`の終了 `]`
+    EndArray,
+    /// `Object`のkey。直後に対応する値のイベントが続く
+    Key(String),
+    /// 文字列
+    String(String),
+    /// 数値
+    Number(Number),
+    /// 真偽値
+    Bool(bool),
+    /// null
+    Null,
+}
+
+/// 読み込み中のコンテナ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Array,
+    Object,
+}
+
+/// 次に読み込む`Token`の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// 値
+    Value,
+    /// `[`の直後。値もしくは`]`
+    ArrayFirst,
+    /// `Array`の要素の後ろ。`,`もしくは`]`
+    ArrayNext,
+    /// `{`の直後。keyもしくは`}`
+    ObjectFirst,
+    /// `Object`の`,`の後ろ。key
+    ObjectKey,
+    /// keyの後ろ。`:`
+    ObjectColon,
+    /// `Object`の値の後ろ。`,`もしくは`}`
+    ObjectNext,
+    /// 値を全て読み終えた
+    Done,
+    /// 入力の終端もしくはエラー。これ以上イベントを返さない
+    Finished,
+}
+
+/// `Token`を一つずつ受け取り、JSONの文法を確認してイベントに変換する状態機械
+/// `EventReader`と`PushParser`で共有する
+pub(crate) struct Grammar {
+    // パースの厳密さ
+    mode: Mode,
+    // ネストの深さの上限
+    max_depth: Option<usize>,
+    // 読み込み中のコンテナ
+    stack: Vec<Container>,
+    // 次に読み込む`Token`の種類
+    state: State,
+}
+
+impl Grammar {
+    pub(crate) fn new(options: ParseOptions) -> Grammar {
+        Grammar {
+            mode: options.mode,
+            max_depth: options.max_depth,
+            stack: vec![],
+            state: State::Value,
+        }
+    }
+
+    /// 値を全て読み終えたかどうかを返す
+    pub(crate) fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// 値を読み終えた後に、次の値を読み込めるようにする
+    pub(crate) fn reset(&mut self) {
+        self.stack.clear();
+        self.state = State::Value;
+    }
+
+    /// `Token`を一つ受け取り、イベントがあれば返す。`token`が`None`の場合は入力の終端(`end`)を表す
+    /// `,`や`:`のようにイベントにならない`Token`や、値を読み終えた後の終端では`None`を返す
+    pub(crate) fn push(
+        &mut self,
+        token: Option<(Token, Span)>,
+        end: Position,
+    ) -> Result<Option<(Event, Span)>, Error> {
+        match self.state {
+            State::Value => self.read_value(token, end).map(Some),
+            State::ArrayFirst => match token {
+                // [ の直後の ] は空配列
+                Some((Token::RightBracket, span)) => Ok(Some(self.close(Event::EndArray, span))),
+                None => Err(unexpected(token, "a value or ']'", end)),
+                _ => self.read_value(token, end).map(Some),
+            },
+            State::ArrayNext => match token {
+                // , なら次の要素(`Value`)を読み込む
+                Some((Token::Comma, _)) => {
+                    self.state = State::Value;
+                    Ok(None)
+                }
+                Some((Token::RightBracket, span)) => Ok(Some(self.close(Event::EndArray, span))),
+                _ => Err(unexpected(token, "']' or ','", end)),
+            },
+            State::ObjectFirst => match token {
+                // { の直後の } は空の`Object`
+                Some((Token::RightBrace, span)) => Ok(Some(self.close(Event::EndObject, span))),
+                None => Err(unexpected(token, "a key or '}'", end)),
+                _ => self.read_key(token, end).map(Some),
+            },
+            State::ObjectKey => self.read_key(token, end).map(Some),
+            State::ObjectColon => match token {
+                // "key" : 12345
+                Some((Token::Colon, _)) => {
+                    self.state = State::Value;
+                    Ok(None)
+                }
+                _ => Err(unexpected(token, "':'", end)),
+            },
+            State::ObjectNext => match token {
+                // , なら次の要素(key-value)を読み込む
+                Some((Token::Comma, _)) => {
+                    self.state = State::ObjectKey;
+                    Ok(None)
+                }
+                Some((Token::RightBrace, span)) => Ok(Some(self.close(Event::EndObject, span))),
+                _ => Err(unexpected(token, "'}' or ','", end)),
+            },
+            State::Done => match token {
+                // e.g. [1] garbage
+                Some((token, span)) => Err(Error::new(
+                    ErrorKind::TrailingData,
+                    &format!("the end of input is expected, but found {}", token),
+                    span.start,
+                )),
+                None => {
+                    self.state = State::Finished;
+                    Ok(None)
+                }
+            },
+            State::Finished => Ok(None),
+        }
+    }
+
+    /// 値の先頭の`Token`を読み込む。`{`と`[`の場合はネストを1段深くする
+    fn read_value(
+        &mut self,
+        token: Option<(Token, Span)>,
+        end: Position,
+    ) -> Result<(Event, Span), Error> {
+        let (token, span) = match token {
+            Some(token) => token,
+            None => return Err(unexpected(token, "a value", end)),
+        };
+        let event = match token {
+            // { はObjectの開始文字
+            Token::LeftBrace => return self.open(Container::Object, span),
+            // [ はArrayの開始文字
+            Token::LeftBracket => return self.open(Container::Array, span),
+            Token::String(s) => Event::String(s),
+            Token::Number(n) => Event::Number(n),
+            Token::Bool(b) => Event::Bool(b),
+            Token::Null => Event::Null,
+            _ => {
+                return Err(unexpected(
+                    Some((token, span)),
+                    "a value ('{', '[', string, number, boolean or null)",
+                    end,
+                ))
+            }
+        };
+        self.end_value();
+        Ok((event, span))
+    }
+
+    /// `Object`のkeyを読み込む
+    fn read_key(
+        &mut self,
+        token: Option<(Token, Span)>,
+        end: Position,
+    ) -> Result<(Event, Span), Error> {
+        match token {
+            Some((Token::String(key), span)) => {
+                self.state = State::ObjectColon;
+                Ok((Event::Key(key), span))
+            }
+            _ => Err(unexpected(token, "a key", end)),
+        }
+    }
+
+    /// コンテナを開始してネストを1段深くする。上限を超えた場合はエラーを返す。
+    fn open(&mut self, container: Container, span: Span) -> Result<(Event, Span), Error> {
+        if let Some(max_depth) = self.max_depth {
+            if self.stack.len() >= max_depth {
+                return Err(Error::new(
+                    ErrorKind::DepthExceeded,
+                    &format!("the nesting depth exceeds the limit {}", max_depth),
+                    span.start,
+                ));
+            }
+        }
+        self.stack.push(container);
+        Ok(match container {
+            Container::Array => {
+                self.state = State::ArrayFirst;
+                (Event::StartArray, span)
+            }
+            Container::Object => {
+                self.state = State::ObjectFirst;
+                (Event::StartObject, span)
+            }
+        })
+    }
+
+    /// コンテナを終了してネストを1段浅くする
+    fn close(&mut self, event: Event, span: Span) -> (Event, Span) {
+        self.stack.pop();
+        self.end_value();
+        (event, span)
+    }
+
+    /// 値を読み終えた後に次に読み込む`Token`の種類を決める
+    fn end_value(&mut self) {
+        self.state = match self.stack.last() {
+            Some(Container::Array) => State::ArrayNext,
+            Some(Container::Object) => State::ObjectNext,
+            None => State::Done,
+        };
+    }
+}
+
+/// `token`が`expected`ではなかったことを示すエラーを返す。`token`が`None`の場合は入力の終端(`end`)を指す
+fn unexpected(token: Option<(Token, Span)>, expected: &str, end: Position) -> Error {
+    match token {
+        Some((token, span)) => Error::new(
+            ErrorKind::UnexpectedToken,
+            &format!("{} is expected, but found {}", expected, token),
+            span.start,
+        ),
+        None => Error::new(
+            ErrorKind::UnexpectedEof,
+            &format!("{} is expected, but found the end of input", expected),
+            end,
+        ),
+    }
+}
+
+/// JSONを`Value`に変換せずにイベントとして一つずつ読み込むパーサー
+/// 各イベントは入力中の範囲(`Span`)を持つ
+///
+/// ```
+/// use monkey_json::{Event, EventReader};
+///
+/// let mut sum = 0.0;
+/// for event in EventReader::new(r#"{"a": [1, 2], "b": {"c": 3}}"#) {
+///     if let (Event::Number(n), _) = event.unwrap() {
+///         sum += n.to_f64_lossy();
+///     }
+/// }
+/// assert_eq!(sum, 6.0);
+/// ```
+pub struct EventReader<'a> {
+    // `Token`の入力元
+    tokens: Box<dyn TokenSource + 'a>,
+    // JSONの文法の状態
+    grammar: Grammar,
+}
+
+impl<'a> EventReader<'a> {
+    /// 文字列を受け取り`EventReader`を返す
+    pub fn new(input: &'a str) -> EventReader<'a> {
+        EventReader::with_options(input, ParseOptions::default())
+    }
+
+    /// 文字列とパースのオプションを受け取り`EventReader`を返す
+    pub fn with_options(input: &'a str, options: ParseOptions) -> EventReader<'a> {
+        EventReader::from_tokens(Lexer::with_options(input, options), options)
+    }
+
+    /// `std::io::Read`を受け取り`EventReader`を返す。入力は少しずつ読み込む
+    pub fn from_reader<R: Read + 'a>(reader: R) -> EventReader<'a> {
+        EventReader::from_reader_with(reader, ParseOptions::default())
+    }
+
+    /// `std::io::Read`とパースのオプションを受け取り`EventReader`を返す
+    pub fn from_reader_with<R: Read + 'a>(reader: R, options: ParseOptions) -> EventReader<'a> {
+        EventReader::from_tokens(Lexer::from_reader(BufReader::new(reader), options), options)
+    }
+
+    /// `Token`の入力元とパースのオプションを受け取り`EventReader`を返す
+    pub(crate) fn from_tokens<T: TokenSource + 'a>(
+        tokens: T,
+        options: ParseOptions,
+    ) -> EventReader<'a> {
+        EventReader {
+            tokens: Box::new(tokens),
+            grammar: Grammar::new(options),
+        }
+    }
+
+    /// 次のイベントとその範囲を返す。値を全て読み終えた場合は`None`を返す
+    /// Strictモードでは値の後ろに`Token`が残っている場合はエラーを返す。エラーの後は`None`を返す
+    pub fn next_event(&mut self) -> Result<Option<(Event, Span)>, Error> {
+        let event = self.read_event();
+        if !matches!(event, Ok(Some(_))) {
+            self.grammar.state = State::Finished;
+        }
+        event
+    }
+
+    /// 入力の終端の位置を返す。`next_event`が`None`を返した後に呼ぶことを想定してる
+    pub(crate) fn end(&self) -> Position {
+        self.tokens.end()
+    }
+
+    /// 現在のネストの深さを返す
+    pub fn depth(&self) -> usize {
+        self.grammar.stack.len()
+    }
+
+    /// イベントになる`Token`まで読み込む
+    fn read_event(&mut self) -> Result<Option<(Event, Span)>, Error> {
+        loop {
+            match self.grammar.state {
+                State::Finished => return Ok(None),
+                // Lenientモードでは値の後ろの入力を読み込まない
+                State::Done if self.grammar.mode == Mode::Lenient => return Ok(None),
+                _ => {}
+            }
+            let token = self.tokens.next_token()?;
+            let end = self.tokens.end();
+            if let Some(event) = self.grammar.push(token, end)? {
+                return Ok(Some(event));
+            }
+        }
+    }
+}
+
+impl Iterator for EventReader<'_> {
+    type Item = Result<(Event, Span), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+
+    fn events(input: &str) -> Vec<Event> {
+        EventReader::new(input)
+            .map(|event| event.unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn test_events() {
+        let json = r#"{"a": [1, "x", true, null, {}], "b": []}"#;
+        assert_eq!(
+            events(json),
+            vec![
+                Event::StartObject,
+                Event::Key("a".to_string()),
+                Event::StartArray,
+                Event::Number(1.into()),
+                Event::String("x".to_string()),
+                Event::Bool(true),
+                Event::Null,
+                Event::StartObject,
+                Event::EndObject,
+                Event::EndArray,
+                Event::Key("b".to_string()),
+                Event::StartArray,
+                Event::EndArray,
+                Event::EndObject,
+            ]
+        );
+        assert_eq!(events("\"top\""), vec![Event::String("top".to_string())]);
+
+        // 各イベントは`Token`の範囲を持つ
+        let spans = EventReader::new("[1,\n \"ab\"]")
+            .map(|event| {
+                let span = event.unwrap().1;
+                (span.start.offset, span.end.offset)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![(0, 1), (1, 2), (5, 9), (9, 10)]);
+    }
+
+    #[test]
+    fn test_events_error() {
+        let mut reader = EventReader::new("[1, {\"a\" 2}]");
+        assert_eq!(reader.next_event().unwrap().unwrap().0, Event::StartArray);
+        assert_eq!(reader.depth(), 1);
+        assert_eq!(
+            reader.next_event().unwrap().unwrap().0,
+            Event::Number(1.into())
+        );
+        assert_eq!(reader.next_event().unwrap().unwrap().0, Event::StartObject);
+        assert_eq!(
+            reader.next_event().unwrap().unwrap().0,
+            Event::Key("a".to_string())
+        );
+        let e = reader.next_event().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedToken);
+        assert_eq!(e.position().offset, 9);
+        // エラーの後はイベントを返さない
+        assert!(reader.next().is_none());
+
+        // Strictモードでは値の後ろの`Token`をエラーにする
+        let mut reader = EventReader::with_options("1 2", ParseOptions::strict());
+        assert_eq!(
+            reader.next_event().unwrap().unwrap().0,
+            Event::Number(1.into())
+        );
+        assert_eq!(
+            reader.next_event().unwrap_err().kind(),
+            ErrorKind::TrailingData
+        );
+        assert_eq!(events("1 2"), vec![Event::Number(1.into())]);
+    }
+
+    #[test]
+    fn test_events_from_reader() {
+        let json = r#"{"a": [1, 2]}"#;
+        let events = EventReader::from_reader(json.as_bytes())
+            .map(|event| event.unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(events, self::events(json));
+    }
+}
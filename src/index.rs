@@ -0,0 +1,168 @@
+use crate::{Map, Value};
+
+/// `Value::get`と`Value::get_mut`の引数として使える型
+/// `str`(`Object`のkey)と`usize`(`Array`の添字)に実装されている
+pub trait Index: private::Sealed {
+    /// `Value`の要素を返す。存在しない場合や型が異なる場合は`None`を返す
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value>;
+
+    /// `Value`の要素を可変参照で返す。存在しない場合や型が異なる場合は`None`を返す
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value>;
+
+    /// `Value`の要素を可変参照で返す。`Object`もしくは`Null`でkeyが存在しない場合は
+    /// `Null`を挿入する(`Null`は空の`Object`に置き換える)
+    /// 添字が範囲外の場合や型が異なる場合はpanicする
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value;
+}
+
+impl Index for usize {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::Array(array) => array.get(*self),
+            _ => None,
+        }
+    }
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::Array(array) => array.get_mut(*self),
+            _ => None,
+        }
+    }
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        match v {
+            Value::Array(array) => {
+                let len = array.len();
+                array
+                    .get_mut(*self)
+                    .unwrap_or_else(|| panic!("An index {} is out of range (len {})", self, len))
+            }
+            _ => panic!("A value is not array"),
+        }
+    }
+}
+
+impl Index for str {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::Object(map) => map.get(self),
+            _ => None,
+        }
+    }
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::Object(map) => map.get_mut(self),
+            _ => None,
+        }
+    }
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        if v.is_null() {
+            *v = Value::Object(Map::new());
+        }
+        match v {
+            Value::Object(map) => map.entry(self).or_insert(Value::Null),
+            _ => panic!("A value is not object"),
+        }
+    }
+}
+
+impl Index for String {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(v)
+    }
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        self.as_str().index_into_mut(v)
+    }
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        self.as_str().index_or_insert(v)
+    }
+}
+
+impl<T: Index + ?Sized> Index for &T {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(v)
+    }
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(v)
+    }
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        (**self).index_or_insert(v)
+    }
+}
+
+/// `Index`をこのクレートの外で実装できないようにする
+mod private {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Value};
+
+    #[test]
+    fn test_get() {
+        let mut v = parse(r#"{"a": [1, "x", true, null, {"b": 1.5}]}"#).unwrap();
+        assert_eq!(
+            v.get("a").and_then(|a| a.get(1)).and_then(Value::as_str),
+            Some("x")
+        );
+        assert_eq!(v["a"].get(0).and_then(Value::as_i64), Some(1));
+        assert_eq!(v["a"].get(2).and_then(Value::as_bool), Some(true));
+        assert!(v["a"][3].is_null());
+        assert_eq!(v["a"][4].get("b").and_then(Value::as_f64), Some(1.5));
+        // 存在しないkeyや添字、型が異なる場合は`None`
+        assert_eq!(v.get("missing"), None);
+        assert_eq!(v["a"].get(10), None);
+        assert_eq!(v.get(0), None);
+        assert_eq!(v["a"].get("a"), None);
+        assert_eq!(v["a"][1].as_f64(), None);
+        assert_eq!(v.get("a".to_string()).map(Value::is_array), Some(true));
+
+        if let Some(Value::String(s)) = v.get_mut("a").and_then(|a| a.get_mut(1)) {
+            s.push('y');
+        }
+        assert_eq!(v["a"][1].as_str(), Some("xy"));
+        v.as_object_mut().unwrap().remove("a");
+        assert_eq!(v.as_object().map(|o| o.len()), Some(0));
+        assert_eq!(v.as_array(), None);
+    }
+
+    #[test]
+    fn test_index_mut() {
+        let mut v = Value::Null;
+        v["a"]["b"] = Value::Number(1.into());
+        v["a"]["c"] = Value::Array(vec![Value::Null]);
+        v["a"]["c"][0] = Value::Bool(true);
+        v["a"]["b"] = Value::String("x".to_string());
+        assert_eq!(v, parse(r#"{"a": {"b": "x", "c": [true]}}"#).unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_mut_out_of_range() {
+        let mut v = Value::Array(vec![]);
+        v[0] = Value::Null;
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_mut_not_object() {
+        let mut v = Value::Bool(true);
+        v["a"] = Value::Null;
+    }
+
+    #[test]
+    fn test_as_number() {
+        let v = parse("[18446744073709551615, -1, 0.5]").unwrap();
+        assert_eq!(v[0].as_u64(), Some(u64::MAX));
+        assert_eq!(v[0].as_i64(), None);
+        assert_eq!(v[1].as_i64(), Some(-1));
+        assert_eq!(v[1].as_u64(), None);
+        assert_eq!(v[2].as_f64(), Some(0.5));
+        assert!(v[2].as_number().unwrap().is_f64());
+        assert_eq!(v.as_array().map(Vec::len), Some(3));
+    }
+}
@@ -1,7 +1,15 @@
+use std::{fmt, io::BufRead};
+
+use crate::{
+    error::INVALID_UTF8,
+    source::{ChunkSource, ReadError, ReadSource, Source, StrSource},
+    Error, ErrorKind, Mode, Number, ParseOptions,
+};
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     String(String), // 文字列
-    Number(f64),    // 数値
+    Number(Number), // 数値
     Bool(bool),     // 真偽値
     Null,           // Null
     WhiteSpace,     // 空白
@@ -13,65 +21,278 @@ pub enum Token {
     Colon,          // :　"key":value 区切り文字
 }
 
-/// JSONの文字列をParseして`Token`単位に分割
-pub struct Lexer<'a> {
-    /// 読み込み中の先頭文字列を指す
-    chars: std::iter::Peekable<std::str::Chars<'a>>,
+/// エラーメッセージ用に`Token`を入力に近い形で表示する
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::String(s) => write!(f, "string {:?}", s),
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Bool(b) => write!(f, "{}", b),
+            Token::Null => write!(f, "null"),
+            Token::WhiteSpace => write!(f, "whitespace"),
+            Token::LeftBrace => write!(f, "'{{'"),
+            Token::RightBrace => write!(f, "'}}'"),
+            Token::LeftBracket => write!(f, "'['"),
+            Token::RightBracket => write!(f, "']'"),
+            Token::Comma => write!(f, "','"),
+            Token::Colon => write!(f, "':'"),
+        }
+    }
 }
 
-/// 字句解析中に発生したエラー
-#[derive(Debug)]
-pub struct LexerError {
-    /// エラーメッセージ
-    pub msg: String,
+/// 入力中の位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// 行(1始まり)
+    pub line: usize,
+    /// 列(1始まり、文字単位)
+    pub column: usize,
+    /// 先頭からのバイト数(0始まり)
+    pub offset: usize,
 }
 
-impl LexerError {
-    fn new(msg: &str) -> LexerError {
-        LexerError {
-            msg: msg.to_string(),
+impl Position {
+    /// 入力の先頭を返す
+    pub fn start() -> Position {
+        Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
+    }
+
+    /// 文字`c`を読み進めた後の位置を返す
+    pub(crate) fn advance(self, c: char) -> Position {
+        if c == '\n' {
+            Position {
+                line: self.line + 1,
+                column: 1,
+                offset: self.offset + c.len_utf8(),
+            }
+        } else {
+            Position {
+                line: self.line,
+                column: self.column + 1,
+                offset: self.offset + c.len_utf8(),
+            }
         }
     }
 }
 
-impl<'a> Lexer<'a> {
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (byte {})",
+            self.line, self.column, self.offset
+        )
+    }
+}
+
+/// `Token`の入力中の範囲。`end`は`Token`の直後の位置を指す
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// 途中まで読み込んだ`Token`。入力の続きが届いた時にその続きから読み込む
+enum Partial {
+    /// 文字列
+    String(PartialString),
+    /// 数値。読み込んだ文字列
+    Number(String),
+    /// リテラル(null, true, false)。期待する文字列、読み込んだ文字列、返す`Token`
+    Literal(&'static str, String, Token),
+}
+
+/// 途中まで読み込んだ文字列
+#[derive(Default)]
+struct PartialString {
+    /// 変換済みの文字列
+    result: String,
+    /// まだ文字列に連結していないUTF-16
+    utf16: Vec<u16>,
+    /// 読み込み中のエスケープ
+    escape: Option<Escape>,
+}
+
+/// 読み込み中のエスケープ。それぞれ'\\'の位置を持つ
+enum Escape {
+    /// '\\'の直後
+    Start(Position),
+    /// `\u`の後の16進数。読み込んだ文字数と16進数の文字
+    Unicode(Position, usize, String),
+}
+
+/// JSONの文字列をParseして`Token`単位に分割
+pub struct Lexer<S> {
+    /// 読み込み中の先頭文字列を指す
+    chars: S,
+    /// 字句解析の厳密さ
+    mode: Mode,
+    /// 数値のリテラルをそのまま保持するかどうか
+    preserve_number_literals: bool,
+    /// 次に読み込む文字の位置
+    position: Position,
+    /// 読み込み中の`Token`の開始位置
+    token_start: Position,
+    /// 入力の続きを待っている`Token`
+    partial: Option<Partial>,
+}
+
+impl<'a> Lexer<StrSource<'a>> {
     /// 文字列を受け取りLexerを返す
-    pub fn new(input: &str) -> Lexer {
+    #[cfg(test)]
+    pub fn new(input: &'a str) -> Lexer<StrSource<'a>> {
+        Lexer::with_options(input, ParseOptions::default())
+    }
+
+    /// 文字列とパースのオプションを受け取りLexerを返す
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Lexer<StrSource<'a>> {
+        Lexer::from_source(StrSource::new(input), options)
+    }
+}
+
+impl Lexer<ChunkSource> {
+    /// 分割して届く入力を読み込むLexerを返す。`feed`で入力の続きを、`finish`で入力の終端を受け取る
+    pub fn chunked(options: ParseOptions) -> Lexer<ChunkSource> {
+        Lexer::from_source(ChunkSource::new(), options)
+    }
+
+    /// 入力の続きを受け取る。途中まで読み込んだ`Token`は次の`next_token`で続きから読み込む
+    pub fn feed(&mut self, s: &str) {
+        self.chars.push_str(s);
+    }
+
+    /// 入力の終端を受け取る
+    pub fn finish(&mut self) {
+        self.chars.finish();
+    }
+}
+
+impl<R: BufRead> Lexer<ReadSource<R>> {
+    /// `BufRead`とパースのオプションを受け取りLexerを返す。入力は`Token`を読み込むたびに少しずつ読み込む
+    pub fn from_reader(reader: R, options: ParseOptions) -> Lexer<ReadSource<R>> {
+        Lexer::from_source(ReadSource::new(reader), options)
+    }
+}
+
+impl<S: Source> Lexer<S> {
+    /// 文字の入力元とパースのオプションを受け取りLexerを返す
+    pub fn from_source(chars: S, options: ParseOptions) -> Lexer<S> {
         Lexer {
-            chars: input.chars().peekable(),
+            chars,
+            mode: options.mode,
+            preserve_number_literals: options.preserve_number_literals,
+            position: Position::start(),
+            token_start: Position::start(),
+            partial: None,
         }
     }
-    /// 文字列をToken単位に分割をする
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, LexerError> {
+
+    /// 入力の先頭を`position`とみなす。入力を分割して読み込む場合に使う
+    pub fn starting_at(mut self, position: Position) -> Lexer<S> {
+        self.position = position;
+        self.token_start = position;
+        self
+    }
+
+    /// 次に読み込む文字の位置を返す。入力を読み終えた場合は入力の終端を指す
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// 文字列をToken単位に分割をする。各`Token`は入力中の範囲(`Span`)を持つ
+    /// `Parser`は`next_token`で一つずつ読み込むので、一覧が必要なテストでのみ使う
+    #[cfg(test)]
+    pub fn tokenize(&mut self) -> Result<Vec<(Token, Span)>, Error> {
         let mut tokens = vec![];
-        while let Some(token) = self.next_token()? {
+        while let Some((token, span)) = self.next_token()? {
             match token {
-                // 空白は今回は捨てるがデバッグ情報として使える(行、列)
+                // 空白は捨てる
                 Token::WhiteSpace => {}
                 _ => {
-                    tokens.push(token);
+                    tokens.push((token, span));
                 }
             }
         }
         Ok(tokens)
     }
 
+    /// 一文字読み進め、位置を更新する
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.position = self.position.advance(c);
+        Some(c)
+    }
+
+    /// 読み込み中の`Token`の開始位置を指すエラーを返す
+    fn error(&self, kind: ErrorKind, msg: &str) -> Error {
+        Error::new(kind, msg, self.token_start)
+    }
+
     /// 一文字分だけ読み進めTokenを返す
     fn next_return_token(&mut self, token: Token) -> Option<Token> {
-        self.chars.next();
+        self.next_char();
         Some(token)
     }
 
+    /// 先頭の文字からマッチしたTokenとその範囲を返す
+    /// 入力の続きが後から届く場合、途中までの`Token`は保存して`None`を返し、続きが届いた後に続きから読み込む
+    pub fn next_token(&mut self) -> Result<Option<(Token, Span)>, Error> {
+        if self.partial.is_none() {
+            self.token_start = self.position;
+        }
+        let token = self.read_token();
+        // 読み込みに失敗した場合は入力の終端として扱われているので、読み込みのエラーを優先して返す
+        match self.chars.take_error() {
+            Some(ReadError::Io(e)) => {
+                return Err(Error::new(
+                    ErrorKind::Io,
+                    &format!("failed to read the input ({})", e),
+                    self.position,
+                ))
+            }
+            Some(ReadError::InvalidUtf8) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidUtf8,
+                    INVALID_UTF8,
+                    self.position,
+                ))
+            }
+            None => {}
+        }
+        let token = token?;
+        Ok(token.map(|token| {
+            (
+                token,
+                Span {
+                    start: self.token_start,
+                    end: self.position,
+                },
+            )
+        }))
+    }
+
     /// 先頭の文字からマッチしたTokenを返す
-    fn next_token(&mut self) -> Result<Option<Token>, LexerError> {
+    fn read_token(&mut self) -> Result<Option<Token>, Error> {
+        // 途中まで読み込んだ`Token`があれば続きから読み込む
+        match self.partial.take() {
+            Some(Partial::String(partial)) => return self.parse_string_token(partial),
+            Some(Partial::Number(number_str)) => return self.parse_number_token(number_str),
+            Some(Partial::Literal(expected, s, token)) => {
+                return self.parse_literal(expected, s, token)
+            }
+            None => {}
+        }
         // 先頭の文字列を読み込む
         match self.chars.peek() {
             Some(c) => match c {
                 // 一文字分だけ読み進め、Tokenを返す
                 // WhiteSpaceは' 'もしくは'\n'
-                c if c.is_whitespace() || *c == '\n' => {
-                    Ok(self.next_return_token(Token::WhiteSpace))
-                }
+                // Strictモードでは' ', '\t', '\n', '\r'のみ
+                c if self.is_whitespace(c) => Ok(self.next_return_token(Token::WhiteSpace)),
                 '{' => Ok(self.next_return_token(Token::LeftBrace)),
                 '}' => Ok(self.next_return_token(Token::RightBrace)),
                 '[' => Ok(self.next_return_token(Token::LeftBracket)),
@@ -86,168 +307,261 @@ impl<'a> Lexer<'a> {
                 // e.g. "togatoga"
                 '"' => {
                     // parse string
-                    self.chars.next();
-                    self.parse_string_token()
+                    self.next_char();
+                    self.parse_string_token(PartialString::default())
                 }
                 // Numberは開始文字が[0-9]もしくは('+', '-', '.')
                 // e.g.
                 //     -1235
                 //     +10
                 //     .00001
-                c if c.is_numeric() || matches!(c, '+' | '-' | '.') => self.parse_number_token(),
+                // Strictモードでは'+', '.'から始まる数値はエラーになる
+                c if c.is_numeric() || matches!(c, '+' | '-' | '.') => {
+                    self.parse_number_token(String::new())
+                }
                 // Booleanの"true"の開始文字は 't'
                 // e.g.
                 //     true
-                't' => self.parse_bool_token(true),
+                't' => self.parse_literal("true", String::new(), Token::Bool(true)),
                 // Boolean("false")の開始文字は't'
                 // e.g.
                 //     false
-                'f' => self.parse_bool_token(false),
+                'f' => self.parse_literal("false", String::new(), Token::Bool(false)),
                 // Nullの開始文字は'n'
                 // e.g.
                 //     null
-                'n' => self.parse_null_token(),
+                'n' => self.parse_literal("null", String::new(), Token::Null),
                 // 上のルールにマッチしない文字はエラー
-                _ => Err(LexerError::new(&format!("error: an unexpected char {}", c))),
+                _ => Err(self.error(
+                    ErrorKind::UnexpectedChar,
+                    &format!(
+                        "a JSON value or a structural char is expected, but found {:?}",
+                        c
+                    ),
+                )),
             },
             None => Ok(None),
         }
     }
 
-    /// nullの文字列をparseする
-    fn parse_null_token(&mut self) -> Result<Option<Token>, LexerError> {
-        let s = (0..4).filter_map(|_| self.chars.next()).collect::<String>();
-
-        if s == "null" {
-            Ok(Some(Token::Null))
-        } else {
-            Err(LexerError::new(&format!(
-                "error: a null value is expected {}",
-                s
-            )))
+    /// 空白文字かどうかを判定する
+    /// https://www.rfc-editor.org/rfc/rfc8259#section-2
+    fn is_whitespace(&self, c: char) -> bool {
+        match self.mode {
+            Mode::Strict => matches!(c, ' ' | '\t' | '\n' | '\r'),
+            Mode::Lenient => c.is_whitespace(),
         }
     }
-    /// (true|false)の文字列をparseする
-    fn parse_bool_token(&mut self, b: bool) -> Result<Option<Token>, LexerError> {
-        if b {
-            let s = (0..4).filter_map(|_| self.chars.next()).collect::<String>();
-            if s == "true" {
-                Ok(Some(Token::Bool(true)))
-            } else {
-                Err(LexerError::new(&format!(
-                    "error: a boolean true is expected {}",
-                    s
-                )))
+
+    /// 入力の続きを待つ必要があるかどうか。読み込める文字がなく、入力の続きが後から届く場合は`true`
+    fn waits_for_input(&mut self) -> bool {
+        !self.chars.is_complete() && self.chars.peek().is_none()
+    }
+
+    /// `expected`のリテラル(null, true, false)を読み込む。`s`は読み込み済みの文字列
+    fn parse_literal(
+        &mut self,
+        expected: &'static str,
+        mut s: String,
+        token: Token,
+    ) -> Result<Option<Token>, Error> {
+        while s.chars().count() < expected.len() {
+            if self.waits_for_input() {
+                self.partial = Some(Partial::Literal(expected, s, token));
+                return Ok(None);
             }
-        } else {
-            let s = (0..5).filter_map(|_| self.chars.next()).collect::<String>();
-
-            if s == "false" {
-                Ok(Some(Token::Bool(false)))
-            } else {
-                Err(LexerError::new(&format!(
-                    "error: a boolean false is expected {}",
-                    s
-                )))
+            match self.next_char() {
+                Some(c) => s.push(c),
+                None => break,
             }
         }
+        if s == expected {
+            Ok(Some(token))
+        } else if expected.starts_with(&s) {
+            // 入力が途中で終わっている e.g. nul
+            Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                &format!("{} is expected, but found the end of input", expected),
+                self.position,
+            ))
+        } else {
+            Err(self.error(
+                ErrorKind::UnexpectedChar,
+                &format!("{} is expected, but found {:?}", expected, s),
+            ))
+        }
     }
 
-    /// 数字として使用可能な文字まで読み込む。読み込んだ文字列が数字(`f64`)としてParseに成功した場合Tokenを返す。
-    fn parse_number_token(&mut self) -> Result<Option<Token>, LexerError> {
-        let mut number_str = String::new();
-        while let Some(&c) = self.chars.peek() {
-            // 数字に使いそうな文字は全て読み込む
-            // 1e10, 1E10, 1.0000
-            if c.is_numeric() | matches!(c, '+' | '-' | 'e' | 'E' | '.') {
-                self.chars.next();
-                number_str.push(c);
-            } else {
-                break;
+    /// 数字として使用可能な文字まで読み込む。読み込んだ文字列が数字(整数もしくは`f64`)としてParseに成功した場合Tokenを返す。
+    /// `number_str`は読み込み済みの文字列
+    fn parse_number_token(&mut self, mut number_str: String) -> Result<Option<Token>, Error> {
+        loop {
+            // 入力の末尾で終わる数値は続きがあるかもしれない e.g. 12|34
+            if self.waits_for_input() {
+                self.partial = Some(Partial::Number(number_str));
+                return Ok(None);
+            }
+            match self.chars.peek() {
+                // 数字に使いそうな文字は全て読み込む
+                // 1e10, 1E10, 1.0000
+                Some(c) if c.is_numeric() | matches!(c, '+' | '-' | 'e' | 'E' | '.') => {
+                    self.next_char();
+                    number_str.push(c);
+                }
+                _ => break,
             }
         }
 
-        // 読み込んだ文字列が`f64`としてparse出来た場合、Tokenを返す
-        match number_str.parse::<f64>() {
-            Ok(number) => Ok(Some(Token::Number(number))),
-            Err(e) => Err(LexerError::new(&format!("error: {}", e))),
+        // Strictモードでは数値の文法に従っているかを確認する
+        if self.mode == Mode::Strict && !is_strict_number(&number_str) {
+            return Err(self.error(
+                ErrorKind::InvalidNumber,
+                &format!("a number is expected, but found {:?}", number_str),
+            ));
+        }
+
+        // 読み込んだ文字列が数値としてparse出来た場合、Tokenを返す
+        let number = if self.preserve_number_literals {
+            Number::from_literal_preserved(&number_str)
+        } else {
+            Number::from_literal(&number_str)
+        };
+        match number {
+            Some(number) => Ok(Some(Token::Number(number))),
+            None => Err(self.error(
+                ErrorKind::InvalidNumber,
+                &format!("a number is expected, but found {:?}", number_str),
+            )),
         }
     }
 
-    /// 終端文字'\"'まで文字列を読み込む。UTF-16(\u0000~\uFFFF)や特殊なエスケープ文字(e.g. '\t','\n')も考慮する
-    fn parse_string_token(&mut self) -> Result<Option<Token>, LexerError> {
-        let mut utf16 = vec![];
-        let mut result = String::new();
-
-        while let Some(c1) = self.chars.next() {
-            match c1 {
-                // Escapeの開始文字'\\'
-                '\\' => {
-                    // 次の文字を読み込む
-                    let c2 = self
-                        .chars
-                        .next()
-                        .ok_or_else(|| LexerError::new("error: a next char is expected"))?;
-                    if matches!(c2, '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') {
+    /// 終端文字'\"'まで文字列を読み込む。UTF-16(\u0000~\uFFFF)や特殊なエスケープ文字(e.g. '\t','\n')は対応する文字に変換する
+    /// `partial`は読み込み済みの文字列
+    fn parse_string_token(&mut self, mut partial: PartialString) -> Result<Option<Token>, Error> {
+        loop {
+            if self.waits_for_input() {
+                self.partial = Some(Partial::String(partial));
+                return Ok(None);
+            }
+            // 読み込む文字の位置(エラー用)
+            let position = self.position;
+            let c = self.next_char();
+            let PartialString {
+                result,
+                utf16,
+                escape,
+            } = &mut partial;
+            match escape.take() {
+                None => match c {
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::UnexpectedEof,
+                            "a closing '\"' is expected, but found the end of input",
+                            self.position,
+                        ))
+                    }
+                    // Escapeの開始文字'\\'
+                    Some('\\') => *escape = Some(Escape::Start(position)),
+                    // 文字列の終端'"'
+                    Some('\"') => {
+                        // utf16のバッファを文字列にpushしておく
+                        self.push_utf16(result, utf16, position)?;
+                        return Ok(Some(Token::String(partial.result)));
+                    }
+                    // Strictモードでは制御文字(U+0000~U+001F)はエスケープが必要
+                    Some(c) if self.mode == Mode::Strict && (c as u32) < 0x20 => {
+                        return Err(Error::new(
+                            ErrorKind::ControlCharacter,
+                            &format!("an escaped char is expected, but found {:?}", c),
+                            position,
+                        ));
+                    }
+                    // それ以外の文字列
+                    Some(c) => {
+                        // utf16のバッファを文字列にpushしておく
+                        self.push_utf16(result, utf16, position)?;
+                        result.push(c);
+                    }
+                },
+                // '\\'の次の文字
+                Some(Escape::Start(start)) => match c {
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::UnexpectedEof,
+                            "an escaped char is expected, but found the end of input",
+                            self.position,
+                        ))
+                    }
+                    Some(c2 @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't')) => {
                         // 特殊なエスケープ文字列の処理
                         // https://www.rfc-editor.org/rfc/rfc8259#section-7
                         // utf16のバッファを文字列にpushしておく
-                        Self::push_utf16(&mut result, &mut utf16)?;
-                        // 今回はエスケープ処理はせずに入力のまま保存しておく
-                        result.push('\\');
-                        result.push(c2);
-                    } else if c2 == 'u' {
-                        // UTF-16
-                        // \u0000 ~ \uFFFF
-                        // \uまで読み込んだので残りの0000~XXXXの4文字を読み込む
-                        // UTF-16に関してはエスケープ処理を行う
-                        let hexs = (0..4)
-                            .filter_map(|_| {
-                                let c = self.chars.next()?;
-                                if c.is_ascii_hexdigit() {
-                                    Some(c)
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect::<Vec<_>>();
-
-                        // 読み込んだ文字列を16進数として評価しutf16のバッファにpushしておく
-                        match u16::from_str_radix(&hexs.iter().collect::<String>(), 16) {
-                            Ok(code_point) => utf16.push(code_point),
-                            Err(e) => {
-                                return Err(LexerError::new(&format!(
-                                    "error: a unicode character is expected {}",
-                                    e
-                                )))
-                            }
-                        };
-                    } else {
-                        return Err(LexerError::new(&format!(
-                            "error: an unexpected escaped char {}",
-                            c2
-                        )));
+                        self.push_utf16(result, utf16, start)?;
+                        // エスケープ文字列を対応する文字に変換して保存する
+                        result.push(match c2 {
+                            'b' => '\u{08}',
+                            'f' => '\u{0c}',
+                            'n' => '\n',
+                            'r' => '\r',
+                            't' => '\t',
+                            // '"', '\\', '/' はそのままの文字
+                            c => c,
+                        });
                     }
-                }
-                // 文字列の終端'"'
-                '\"' => {
-                    // utf16のバッファを文字列にpushしておく
-                    Self::push_utf16(&mut result, &mut utf16)?;
-                    return Ok(Some(Token::String(result)));
-                }
-                // それ以外の文字列
-                _ => {
-                    // utf16のバッファを文字列にpushしておく
-                    Self::push_utf16(&mut result, &mut utf16)?;
-                    result.push(c1);
+                    // UTF-16
+                    // \u0000 ~ \uFFFF
+                    // \uまで読み込んだので残りの0000~XXXXの4文字を読み込む
+                    Some('u') => *escape = Some(Escape::Unicode(start, 0, String::new())),
+                    Some(c2) => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidEscape,
+                            &format!("an escaped char is expected, but found {:?}", c2),
+                            start,
+                        ));
+                    }
+                },
+                // `\u`の後の4文字
+                Some(Escape::Unicode(start, count, mut hexs)) => {
+                    match c {
+                        Some(c) if c.is_ascii_hexdigit() => hexs.push(c),
+                        // Strictモードでは必ず4桁の16進数が必要
+                        c if self.mode == Mode::Strict => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidEscape,
+                                &format!("4 hex digits are expected after \\u, but found {:?}", c),
+                                start,
+                            ))
+                        }
+                        // Lenientモードでは16進数以外の文字を読み飛ばす
+                        _ => {}
+                    }
+                    if count + 1 < 4 {
+                        *escape = Some(Escape::Unicode(start, count + 1, hexs));
+                        continue;
+                    }
+                    // 読み込んだ文字列を16進数として評価しutf16のバッファにpushしておく
+                    match u16::from_str_radix(&hexs, 16) {
+                        Ok(code_point) => utf16.push(code_point),
+                        Err(e) => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidEscape,
+                                &format!("a unicode character is expected after \\u ({})", e),
+                                start,
+                            ))
+                        }
+                    };
                 }
             }
         }
-        Ok(None)
     }
 
-    /// utf16のバッファが存在するならば連結しておく
-    fn push_utf16(result: &mut String, utf16: &mut Vec<u16>) -> Result<(), LexerError> {
+    /// utf16のバッファが存在するならば連結しておく。`position`はバッファの直後の位置
+    fn push_utf16(
+        &self,
+        result: &mut String,
+        utf16: &mut Vec<u16>,
+        position: Position,
+    ) -> Result<(), Error> {
         if utf16.is_empty() {
             return Ok(());
         }
@@ -257,13 +571,63 @@ impl<'a> Lexer<'a> {
                 utf16.clear();
             }
             Err(e) => {
-                return Err(LexerError::new(&format!("error: {}", e)));
+                return Err(Error::new(
+                    ErrorKind::InvalidUnicode,
+                    &format!("a valid UTF-16 surrogate pair is expected ({})", e),
+                    position,
+                ));
             }
         };
         Ok(())
     }
 }
 
+/// RFC8259の数値の文法に従っているかを判定する
+/// number = [ minus ] int [ frac ] [ exp ]
+/// https://www.rfc-editor.org/rfc/rfc8259#section-6
+pub(crate) fn is_strict_number(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while bytes.get(*i).is_some_and(u8::is_ascii_digit) {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    // minus
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    // int = zero / ( digit1-9 *DIGIT )
+    match bytes.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => {
+            digits(&mut i);
+        }
+        _ => return false,
+    }
+    // frac = decimal-point 1*DIGIT
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    // exp = e [ minus / plus ] 1*DIGIT
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,74 +636,171 @@ mod tests {
         //integer
         let num = "1234567890";
         let tokens = Lexer::new(num).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Number(1234567890f64));
+        assert_eq!(tokens[0].0, Token::Number(1234567890.into()));
 
         let num = "+123";
         let tokens = Lexer::new(num).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Number(123f64));
+        assert_eq!(tokens[0].0, Token::Number(123.into()));
 
         //float
         let num = "-0.001";
         let tokens = Lexer::new(num).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Number(-0.001));
+        assert_eq!(tokens[0].0, Token::Number((-0.001).into()));
 
         let num = ".001";
         let tokens = Lexer::new(num).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Number(0.001));
+        assert_eq!(tokens[0].0, Token::Number(0.001.into()));
 
         // exponent
         let num = "1e-10";
         let tokens = Lexer::new(num).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Number(0.0000000001));
+        assert_eq!(tokens[0].0, Token::Number(0.0000000001.into()));
 
         let num = "+2E10";
         let tokens = Lexer::new(num).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Number(20000000000f64));
+        assert_eq!(tokens[0].0, Token::Number(20000000000f64.into()));
+    }
+
+    #[test]
+    fn test_integer() {
+        // 2^53 + 1 は`f64`では表現できないが整数として保持する
+        let num = "[9007199254740993, -9007199254740993, 1.0]";
+        let tokens = Lexer::new(num).tokenize().unwrap();
+        let numbers = tokens
+            .into_iter()
+            .filter_map(|(token, _)| match token {
+                Token::Number(n) => Some(n),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(numbers[0].as_u64(), Some(9_007_199_254_740_993));
+        assert_eq!(numbers[1].as_i64(), Some(-9_007_199_254_740_993));
+        assert!(numbers[2].is_f64());
+    }
+
+    #[test]
+    fn test_preserve_number_literals() {
+        let options = ParseOptions {
+            preserve_number_literals: true,
+            ..ParseOptions::default()
+        };
+        let num = "[1.10, 1e2, 12345678901234567890123, +1]";
+        let tokens = Lexer::with_options(num, options).tokenize().unwrap();
+        let numbers = tokens
+            .into_iter()
+            .filter_map(|(token, _)| match token {
+                Token::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec!["1.10", "1e2", "12345678901234567890123", "1"]);
+    }
+
+    #[test]
+    fn test_strict_number() {
+        let strict = ParseOptions::strict();
+        for num in ["0", "-0", "123", "-1.5", "1e10", "1E+2", "0.5e-3"] {
+            assert!(
+                Lexer::with_options(num, strict).tokenize().is_ok(),
+                "{}",
+                num
+            );
+        }
+        for num in ["+1", ".5", "01", "1.", "1e", "-", "1.e5", "--1", "1e+"] {
+            assert!(
+                Lexer::with_options(num, strict).tokenize().is_err(),
+                "{}",
+                num
+            );
+            // Lenientモードでは先頭の"+", "."や末尾の"."を許容する
+            if matches!(num, "+1" | ".5" | "01" | "1.") {
+                assert!(Lexer::new(num).tokenize().is_ok(), "{}", num);
+            }
+        }
     }
 
     #[test]
     fn test_bool() {
         let b = "true";
         let tokens = Lexer::new(b).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Bool(true));
+        assert_eq!(tokens[0].0, Token::Bool(true));
 
         let b = "false";
         let tokens = Lexer::new(b).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Bool(false));
+        assert_eq!(tokens[0].0, Token::Bool(false));
     }
 
     #[test]
     fn test_string() {
         let s = "\"togatoga123\"";
         let tokens = Lexer::new(s).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::String("togatoga123".to_string()));
+        assert_eq!(tokens[0].0, Token::String("togatoga123".to_string()));
 
         let s = "\"あいうえお\"";
         let tokens = Lexer::new(s).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::String("あいうえお".to_string()));
+        assert_eq!(tokens[0].0, Token::String("あいうえお".to_string()));
 
         let s = r#""\u3042\u3044\u3046abc""#; //あいうabc
 
         let tokens = Lexer::new(s).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::String("あいうabc".to_string()));
+        assert_eq!(tokens[0].0, Token::String("あいうabc".to_string()));
 
         let s = r#" " \b \f \n \r \t \/ \" ""#.to_string();
         let tokens = Lexer::new(&s).tokenize().unwrap();
         assert_eq!(
-            tokens[0],
-            Token::String(r#" \b \f \n \r \t \/ \" "#.to_string())
+            tokens[0].0,
+            Token::String(" \u{08} \u{0c} \n \r \t / \" ".to_string())
         );
 
+        let s = r#""a\\nb\u000a\u0022""#;
+        let tokens = Lexer::new(s).tokenize().unwrap();
+        assert_eq!(tokens[0].0, Token::String("a\\nb\n\"".to_string()));
+
         let s = r#""\uD83D\uDE04\uD83D\uDE07\uD83D\uDC7A""#;
         let tokens = Lexer::new(s).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::String(r#"😄😇👺"#.to_string()));
+        assert_eq!(tokens[0].0, Token::String(r#"😄😇👺"#.to_string()));
+    }
+
+    #[test]
+    fn test_strict_string() {
+        let strict = ParseOptions::strict();
+        // 4桁未満の\u
+        let s = r#""\u12""#;
+        assert!(Lexer::with_options(s, strict).tokenize().is_err());
+        let s = r#""\u12x4""#;
+        assert!(Lexer::with_options(s, strict).tokenize().is_err());
+        assert!(Lexer::new(s).tokenize().is_ok());
+
+        // エスケープされていない制御文字
+        let s = "\"a\tb\"";
+        assert!(Lexer::with_options(s, strict).tokenize().is_err());
+        assert_eq!(
+            Lexer::new(s).tokenize().unwrap()[0].0,
+            Token::String("a\tb".to_string())
+        );
+
+        let s = r#""\u0041\t""#;
+        let tokens = Lexer::with_options(s, strict).tokenize().unwrap();
+        assert_eq!(tokens[0].0, Token::String("A\t".to_string()));
+    }
+
+    #[test]
+    fn test_strict_whitespace() {
+        let strict = ParseOptions::strict();
+        let s = " \t\r\n[ null ]\n";
+        assert!(Lexer::with_options(s, strict).tokenize().is_ok());
+
+        // U+3000(全角スペース)やU+00A0はJSONの空白ではない
+        let s = "[\u{3000}null\u{a0}]";
+        assert!(Lexer::with_options(s, strict).tokenize().is_err());
+        assert!(Lexer::new(s).tokenize().is_ok());
     }
 
     #[test]
     fn test_null() {
         let null = "null";
         let tokens = Lexer::new(null).tokenize().unwrap();
-        assert_eq!(tokens[0], Token::Null);
+        assert_eq!(tokens[0].0, Token::Null);
     }
 
     #[test]
@@ -362,7 +823,7 @@ mod tests {
             // begin: "number": 123,
             Token::String("number".to_string()),
             Token::Colon,
-            Token::Number(123f64),
+            Token::Number(123.into()),
             Token::Comma,
             // end
 
@@ -387,7 +848,7 @@ mod tests {
             // begin: "number": 2E10,
             Token::String("number".to_string()),
             Token::Colon,
-            Token::Number(20000000000f64),
+            Token::Number(20000000000f64.into()),
             // end
             Token::RightBrace,
             // end
@@ -398,7 +859,7 @@ mod tests {
             .iter()
             .zip(result_tokens.iter())
             .enumerate()
-            .for_each(|(i, (x, y))| {
+            .for_each(|(i, ((x, _), y))| {
                 assert_eq!(x, y, "index: {}", i);
             });
 
@@ -419,6 +880,56 @@ mod tests {
         tokens
             .iter()
             .zip(result_tokens.iter())
-            .for_each(|(x, y)| assert_eq!(x, y));
+            .for_each(|((x, _), y)| assert_eq!(x, y));
+    }
+
+    #[test]
+    fn test_span() {
+        let s = "{\n  \"あ\": [true,\n 12]}";
+        let tokens = Lexer::new(s).tokenize().unwrap();
+        let starts = tokens
+            .iter()
+            .map(|(_, span)| (span.start.line, span.start.column, span.start.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            starts,
+            vec![
+                (1, 1, 0),   // {
+                (2, 3, 4),   // "あ"
+                (2, 6, 9),   // :
+                (2, 8, 11),  // [
+                (2, 9, 12),  // true
+                (2, 13, 16), // ,
+                (3, 2, 19),  // 12
+                (3, 4, 21),  // ]
+                (3, 5, 22),  // }
+            ]
+        );
+        let (_, span) = &tokens[1];
+        assert_eq!(span.end.offset, 9);
+        assert_eq!(&s[span.start.offset..span.end.offset], "\"あ\"");
+    }
+
+    #[test]
+    fn test_error_position() {
+        let s = "[1,\n  nul]";
+        let e = Lexer::new(s).tokenize().unwrap_err();
+        assert_eq!(
+            e.position(),
+            Position {
+                line: 2,
+                column: 3,
+                offset: 6
+            }
+        );
+
+        let s = r#"["ab\x"]"#;
+        let e = Lexer::new(s).tokenize().unwrap_err();
+        assert_eq!(e.position().offset, 4);
+        assert!(e.message().contains("'x'"), "{}", e);
+
+        let s = r#"["abc"#;
+        let e = Lexer::new(s).tokenize().unwrap_err();
+        assert_eq!(e.position().offset, 5);
     }
 }
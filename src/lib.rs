@@ -1,28 +1,147 @@
-use std::collections::BTreeMap;
-
+pub use diff::{diff, diff_preserving_order};
+pub use error::{Error, ErrorKind};
+pub use event::{Event, EventReader};
+pub use index::Index;
 use lexer::Lexer;
-use parser::{Parser, ParserError};
+pub use lexer::{Position, Span};
+pub use lines::{JsonLinesReader, JsonLinesWriter};
+pub use map::Map;
+pub use merge3::{merge3, Conflict, MergeConflict};
+pub use merge_patch::create_merge_patch;
+pub use number::{Decimal, Number};
+use parser::Parser;
+pub use patch::{PatchError, PatchErrorKind, PatchOperation};
+pub use pointer::{PointerError, PointerErrorKind};
+pub use push::PushParser;
+pub use serializer::{to_string, to_string_pretty, to_string_with, WriteOptions};
+pub use typed::{from_value, to_value, ConvertError, FromValue, ToObject, ToValue};
 
+mod convert;
+mod diff;
+mod error;
+mod event;
+mod index;
 mod lexer;
+mod lines;
+mod macros;
+pub mod map;
+mod merge3;
+mod merge_patch;
+mod number;
 mod parser;
+mod patch;
+mod pointer;
+mod push;
+mod serializer;
+mod source;
+#[cfg(test)]
+mod test_util;
+mod typed;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
-    String(String),                  // 文字列
-    Number(f64),                     // 数値
-    Bool(bool),                      // 真偽値
-    Null,                            // Null
-    Array(Vec<Value>),               // JSON Array
-    Object(BTreeMap<String, Value>), // JSON Object
+    String(String),    // 文字列
+    Number(Number),    // 数値
+    Bool(bool),        // 真偽値
+    Null,              // Null
+    Array(Vec<Value>), // JSON Array
+    Object(Map),       // JSON Object
+}
+
+/// パースの厳密さ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// RFC8259に厳密に従う
+    /// https://www.rfc-editor.org/rfc/rfc8259
+    Strict,
+    /// 数値の文法や空白などを緩く扱う(e.g. `+1`, `.5`, `[1] garbage`)
+    Lenient,
+}
+
+/// `Object`のkeyが重複した場合の扱い
+/// e.g. {"key": 1, "key": 2}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// エラーにする
+    Error,
+    /// 最初の値を残す({"key": 1})
+    FirstWins,
+    /// 最後の値を残す({"key": 2})。keyの位置は最初の出現位置のまま
+    LastWins,
+}
+
+/// `ParseOptions::max_depth`に指定するネストの深さの上限の目安
+/// `ParseOptions::strict()`と`ParseOptions::lenient()`は上限を設けない
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// パースのオプション
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// パースの厳密さ
+    pub mode: Mode,
+    /// ネストの深さの上限。`None`なら上限なし
+    pub max_depth: Option<usize>,
+    /// `Object`のkeyが重複した場合の扱い
+    pub duplicate_keys: DuplicateKeys,
+    /// 数値のリテラルをそのまま保持する(e.g. 1.10, 40桁の整数)
+    /// RFC8259の文法に従わないリテラル(e.g. +1)は通常の数値として扱う
+    pub preserve_number_literals: bool,
+}
+
+impl ParseOptions {
+    /// RFC8259に厳密に従うオプションを返す
+    pub fn strict() -> ParseOptions {
+        ParseOptions {
+            mode: Mode::Strict,
+            max_depth: None,
+            duplicate_keys: DuplicateKeys::LastWins,
+            preserve_number_literals: false,
+        }
+    }
+
+    /// 緩くパースするオプションを返す
+    pub fn lenient() -> ParseOptions {
+        ParseOptions {
+            mode: Mode::Lenient,
+            max_depth: None,
+            duplicate_keys: DuplicateKeys::LastWins,
+            preserve_number_literals: false,
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions::lenient()
+    }
 }
 
 /// ユーザーのエンドポイント
 /// 入力のJSONの文字列から`Value`を返す
-pub fn parse(input: &str) -> Result<Value, ParserError> {
-    match Lexer::new(input).tokenize() {
-        Ok(tokens) => Parser::new(tokens).parse(),
-        Err(e) => Err(ParserError::new(&e.msg)),
-    }
+pub fn parse(input: &str) -> Result<Value, Error> {
+    parse_with(input, ParseOptions::default())
+}
+
+/// 入力のJSONの文字列をオプションに従ってパースして`Value`を返す
+pub fn parse_with(input: &str, options: ParseOptions) -> Result<Value, Error> {
+    // `Parser`は必要になった時点で`Lexer`から`Token`を一つずつ読み込む
+    let lexer = Lexer::with_options(input, options);
+    Parser::with_options(lexer, options).parse_document()
+}
+
+/// `std::io::Read`からJSONを読み込んで`Value`を返す
+/// 入力はバッファ経由で少しずつ字句解析するので、入力全体を文字列や`Token`の一覧として保持しない
+pub fn parse_reader<R: std::io::Read>(reader: R) -> Result<Value, Error> {
+    parse_reader_with(reader, ParseOptions::default())
+}
+
+/// `std::io::Read`からJSONを読み込んでオプションに従ってパースする
+/// `Lenient`モードでは値の後ろの入力は読み込まない
+pub fn parse_reader_with<R: std::io::Read>(
+    reader: R,
+    options: ParseOptions,
+) -> Result<Value, Error> {
+    Parser::from_events(EventReader::from_reader_with(reader, options), options).parse_document()
 }
 
 /// {"key": true}
@@ -42,7 +161,7 @@ impl std::ops::Index<&str> for Value {
 }
 
 /// [null, false, 3]
-/// v[3] => Value::Number(3f64)
+/// v[2] => Value::Number(3.into())
 impl std::ops::Index<usize> for Value {
     type Output = Value;
     fn index(&self, idx: usize) -> &Self::Output {
@@ -54,3 +173,153 @@ impl std::ops::Index<usize> for Value {
         }
     }
 }
+
+/// keyが存在しない場合は`Null`を挿入する。`Null`に対しては空の`Object`を作る
+/// v["a"]["b"] = Value::Bool(true) => {"a": {"b": true}}
+impl std::ops::IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Self::Output {
+        key.index_or_insert(self)
+    }
+}
+
+/// 添字が範囲外の場合はpanicする
+impl std::ops::IndexMut<usize> for Value {
+    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+        idx.index_or_insert(self)
+    }
+}
+
+impl Value {
+    /// `Object`のkeyもしくは`Array`の添字に対応する要素を返す。
+    /// 存在しない場合や型が異なる場合は`None`を返す(panicしない)
+    /// e.g. v.get("key"), v.get(0)
+    pub fn get<I: Index>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    /// `Object`のkeyもしくは`Array`の添字に対応する要素を可変参照で返す
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+
+    /// エラーメッセージ用の型名を返す
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+
+    /// `Null`かどうかを返す
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// `Bool`かどうかを返す
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Value::Bool(_))
+    }
+
+    /// `Number`かどうかを返す
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Number(_))
+    }
+
+    /// `String`かどうかを返す
+    pub fn is_string(&self) -> bool {
+        matches!(self, Value::String(_))
+    }
+
+    /// `Array`かどうかを返す
+    pub fn is_array(&self) -> bool {
+        matches!(self, Value::Array(_))
+    }
+
+    /// `Object`かどうかを返す
+    pub fn is_object(&self) -> bool {
+        matches!(self, Value::Object(_))
+    }
+
+    /// `Bool`なら値を返す
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// `Number`なら値を返す
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// `Number`で`f64`として正確に表現できるなら値を返す
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().and_then(Number::as_f64)
+    }
+
+    /// `Number`で`i64`として表現できるなら値を返す
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    /// `Number`で`u64`として表現できるなら値を返す
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    /// `String`なら文字列を返す
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// `String`なら文字列を可変参照で返す
+    pub fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// `Array`なら要素の一覧を返す
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// `Array`なら要素の一覧を可変参照で返す
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// `Object`ならkey-valueの一覧を返す
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// `Object`ならkey-valueの一覧を可変参照で返す
+    pub fn as_object_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::{
+    error::INVALID_UTF8, event::TokenSource, lexer::Lexer, parser::Parser, to_string, Error,
+    ErrorKind, ParseOptions, Position, Value,
+};
+
+/// 改行区切りのJSON(NDJSON / JSON Lines)を一行ずつ読み込む
+/// 各行をパースした値とその行番号(1始まり)を返す。JSONの空白だけの行は読み飛ばす
+/// パースに失敗した行はエラーを返し、次の行から読み込みを続ける。読み込み自体に失敗した場合はそこで終わる
+/// Lenientモードでも、値の後ろに空白以外がある行(e.g. `{"id":1} {"id":2}`)はエラーにする
+///
+/// ```
+/// use monkey_json::JsonLinesReader;
+///
+/// let input = "{\"id\": 1}\n\n{\"id\": 2,}\n{\"id\": 3}\n";
+/// let mut lines = JsonLinesReader::new(input.as_bytes());
+/// assert_eq!(lines.next().unwrap().unwrap(), (1, monkey_json::parse(r#"{"id": 1}"#).unwrap()));
+/// assert_eq!(lines.next().unwrap().unwrap_err().position().line, 3);
+/// assert_eq!(lines.next().unwrap().unwrap().0, 4);
+/// assert!(lines.next().is_none());
+/// ```
+pub struct JsonLinesReader<R> {
+    reader: BufReader<R>,
+    // パースのオプション
+    options: ParseOptions,
+    // 読み込み中の行
+    buffer: Vec<u8>,
+    // 次に読み込む行の先頭の位置
+    position: Position,
+    // 入力を読み終えたかどうか
+    finished: bool,
+}
+
+impl<R: Read> JsonLinesReader<R> {
+    /// `std::io::Read`を受け取り`JsonLinesReader`を返す
+    pub fn new(reader: R) -> JsonLinesReader<R> {
+        JsonLinesReader::with_options(reader, ParseOptions::default())
+    }
+
+    /// `std::io::Read`とパースのオプションを受け取り`JsonLinesReader`を返す
+    /// オプションは各行に`parse_with`と同じように適用する
+    pub fn with_options(reader: R, options: ParseOptions) -> JsonLinesReader<R> {
+        JsonLinesReader {
+            reader: BufReader::new(reader),
+            options,
+            buffer: vec![],
+            position: Position::start(),
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for JsonLinesReader<R> {
+    type Item = Result<(usize, Value), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.finished {
+                return None;
+            }
+            self.buffer.clear();
+            let start = self.position;
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => {
+                    self.finished = true;
+                    return None;
+                }
+                Ok(len) => {
+                    self.position = Position {
+                        line: start.line + 1,
+                        column: 1,
+                        offset: start.offset + len,
+                    };
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(Error::new(
+                        ErrorKind::Io,
+                        &format!("failed to read the input ({})", e),
+                        start,
+                    )));
+                }
+            }
+
+            let line = match std::str::from_utf8(&self.buffer) {
+                Ok(line) => line,
+                Err(e) => {
+                    // 不正なバイトの位置を指す
+                    let valid = std::str::from_utf8(&self.buffer[..e.valid_up_to()])
+                        .expect("validated UTF-8");
+                    return Some(Err(Error::new(
+                        ErrorKind::InvalidUtf8,
+                        INVALID_UTF8,
+                        valid.chars().fold(start, Position::advance),
+                    )));
+                }
+            };
+            // 行末の改行は含めない(途中で終わっている値のエラーがその行を指すように)
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            // JSONの空白だけの行は読み飛ばす(Unicodeの空白だけの行は値がないのでエラーにする)
+            if line
+                .trim_matches(|c| matches!(c, ' ' | '\t' | '\r'))
+                .is_empty()
+            {
+                continue;
+            }
+            // エラーの位置が入力全体での位置になるように、行の先頭の位置から字句解析する
+            let mut lexer = Lexer::with_options(line, self.options).starting_at(start);
+            let value = Parser::with_options(&mut lexer, self.options).parse_document();
+            // Lenientモードでは値の後ろを読み込まないので、残りの`Token`を確かめる
+            let value = value.and_then(|value| match TokenSource::next_token(&mut lexer)? {
+                Some((token, span)) => Err(Error::new(
+                    ErrorKind::TrailingData,
+                    &format!("the end of input is expected, but found {}", token),
+                    span.start,
+                )),
+                None => Ok(value),
+            });
+            return Some(value.map(|value| (start.line, value)));
+        }
+    }
+}
+
+/// 値を一行に一つずつ空白なしのJSONとして書き出す(NDJSON / JSON Lines)
+pub struct JsonLinesWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    /// `std::io::Write`を受け取り`JsonLinesWriter`を返す
+    pub fn new(writer: W) -> JsonLinesWriter<W> {
+        JsonLinesWriter { writer }
+    }
+
+    /// 値を一行書き出す
+    pub fn write(&mut self, value: &Value) -> io::Result<()> {
+        let mut line = to_string(value);
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())
+    }
+
+    /// 書き出した内容をフラッシュする
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// 内部の`std::io::Write`を返す
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_read_lines() {
+        let input = b"{\"a\": 1}\r\n\n  \n[1,\n\"x\"\n{\"a\": [1 2]}\n\xff\n3";
+        let results = JsonLinesReader::new(&input[..]).collect::<Vec<_>>();
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[0], Ok((1, json!({"a": 1}))));
+        // 行の途中で終わっている値はその行のエラーになる
+        let e = results[1].as_ref().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(e.position().line, 4);
+        assert_eq!(results[2], Ok((5, json!("x"))));
+        // エラーの位置は入力全体での位置
+        let e = results[3].as_ref().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedToken);
+        assert_eq!((e.position().line, e.position().column), (6, 10));
+        assert_eq!(e.position().offset, 31);
+        assert_eq!(
+            results[4].as_ref().unwrap_err().kind(),
+            ErrorKind::InvalidUtf8
+        );
+        // 最後の行は改行がなくてもよい
+        assert_eq!(results[5], Ok((8, json!(3))));
+
+        // 一行に値が1つだけでない場合はモードによらずエラー
+        let input = "{\"id\":1} {\"id\":2}\n[1] garbage\n[2]  \n[3] 4\n";
+        for options in [ParseOptions::strict(), ParseOptions::lenient()] {
+            let results =
+                JsonLinesReader::with_options(input.as_bytes(), options).collect::<Vec<_>>();
+            assert_eq!(results.len(), 4);
+            let e = results[0].as_ref().unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::TrailingData);
+            assert_eq!((e.position().line, e.position().column), (1, 10));
+            let e = results[1].as_ref().unwrap_err();
+            assert_eq!(e.position().line, 2);
+            assert_eq!(results[2], Ok((3, json!([2]))));
+            let e = results[3].as_ref().unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::TrailingData);
+            assert_eq!(e.position().offset, 40);
+        }
+
+        // JSONの空白ではない文字だけの行は読み飛ばさない
+        let input = "1\n\t \r\n\u{3000}\n\u{a0}\n2\n";
+        let results = JsonLinesReader::with_options(input.as_bytes(), ParseOptions::strict())
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0], Ok((1, json!(1))));
+        let e = results[1].as_ref().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedChar);
+        assert_eq!(e.position().line, 3);
+        assert_eq!(results[2].as_ref().unwrap_err().position().line, 4);
+        assert_eq!(results[3], Ok((5, json!(2))));
+    }
+
+    #[test]
+    fn test_write_lines() {
+        let values = vec![json!({"a": [1, "x\ny"]}), json!(null), json!({})];
+        let mut writer = JsonLinesWriter::new(vec![]);
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        writer.flush().unwrap();
+        let output = writer.into_inner();
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            "{\"a\":[1,\"x\\ny\"]}\nnull\n{}\n"
+        );
+        let read = JsonLinesReader::new(&output[..])
+            .map(|result| result.unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(read, values);
+    }
+}
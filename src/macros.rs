@@ -0,0 +1,205 @@
+/// JSONに近い記法で`Value`を作る。値の位置にはRustの式も書ける
+/// `Object`のkeyは文字列リテラルか、`String`に変換できる式を`()`で囲んで書く
+///
+/// ```
+/// use monkey_json::json;
+///
+/// let name = "mj";
+/// let value = json!({
+///     "name": name,
+///     "tags": ["json", null, 1 + 2],
+///     (format!("{}_version", name)): { "major": 0, "minor": 1.5 },
+///     "enabled": true
+/// });
+/// assert_eq!(value["tags"][2], json!(3));
+/// assert_eq!(value["mj_version"]["major"], json!(0));
+/// ```
+#[macro_export]
+macro_rules! json {
+    ($($json:tt)+) => {
+        $crate::json_internal!($($json)+)
+    };
+}
+
+/// `json!`の実装。tokenを先頭から1つずつ読み進める
+#[macro_export]
+#[doc(hidden)]
+macro_rules! json_internal {
+    // ---- Array ----
+    // 全ての要素を読み終えた
+    (@array [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+    (@array [$($elems:expr),*]) => {
+        vec![$($elems),*]
+    };
+    // 次の要素がリテラルもしくはネストした`Array`, `Object`
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!(null)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!(true)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!(false)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!([$($array)*])] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($object:tt)*} $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!({$($object)*})] $($rest)*)
+    };
+    // 次の要素が式で、後ろに','が続く
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!($next),] $($rest)*)
+    };
+    // 最後の要素が式
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!($last)])
+    };
+    // 要素の後ろの','
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)*] $($rest)*)
+    };
+    // 要素の後ろに予期しないtoken
+    (@array [$($elems:expr),*] $unexpected:tt $($rest:tt)*) => {
+        $crate::json_internal!(@unexpected $unexpected)
+    };
+
+    // ---- Object ----
+    // (key) (残りのtoken) (エラー表示用の残りのtokenの複製)の形で読み進める
+    // 全てのkey-valueを読み終えた
+    (@object $object:ident () () ()) => {};
+    // key-valueを挿入し、','の後ろを読み進める
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+        $crate::json_internal!(@object $object () ($($rest)*) ($($rest)*));
+    };
+    // key-valueの後ろに予期しないtoken
+    (@object $object:ident [$($key:tt)+] ($value:expr) $unexpected:tt $($rest:tt)*) => {
+        $crate::json_internal!(@unexpected $unexpected);
+    };
+    // 最後のkey-valueを挿入する
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+    };
+    // 値がリテラルもしくはネストした`Array`, `Object`
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(null)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(true)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(false)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!([$($array)*])) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!({$($map)*})) $($rest)*);
+    };
+    // 値が式で、後ろに','が続く
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!($value)) , $($rest)*);
+    };
+    // 最後の値が式
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!($value)));
+    };
+    // ':'の後ろに値がない
+    (@object $object:ident ($($key:tt)+) (:) $copy:tt) => {
+        $crate::json_internal!();
+    };
+    // keyの後ろに':'がない
+    (@object $object:ident ($($key:tt)+) () $copy:tt) => {
+        $crate::json_internal!();
+    };
+    // keyがない
+    (@object $object:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        $crate::json_internal!(@unexpected $colon);
+    };
+    // keyの中に','がある
+    (@object $object:ident ($($key:tt)*) (, $($rest:tt)*) ($comma:tt $($copy:tt)*)) => {
+        $crate::json_internal!(@unexpected $comma);
+    };
+    // `()`で囲まれたkey
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+    // keyのtokenを1つ読み進める
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    // どのルールにも一致しないので、tokenの位置でコンパイルエラーになる
+    (@unexpected) => {};
+
+    // ---- 値 ----
+    (null) => {
+        $crate::Value::Null
+    };
+    (true) => {
+        $crate::Value::Bool(true)
+    };
+    (false) => {
+        $crate::Value::Bool(false)
+    };
+    ([]) => {
+        $crate::Value::Array(vec![])
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::Value::Array($crate::json_internal!(@array [] $($tt)+))
+    };
+    ({}) => {
+        $crate::Value::Object($crate::Map::new())
+    };
+    ({ $($tt:tt)+ }) => {
+        $crate::Value::Object({
+            let mut object = $crate::Map::new();
+            $crate::json_internal!(@object object () ($($tt)+) ($($tt)+));
+            object
+        })
+    };
+    // それ以外は`From`で変換できる式
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Value};
+
+    #[test]
+    fn test_json() {
+        assert_eq!(json!(null), Value::Null);
+        assert_eq!(json!(true), Value::Bool(true));
+        assert_eq!(json!(-1), Value::Number((-1).into()));
+        assert_eq!(json!("a"), Value::String("a".to_string()));
+        assert_eq!(json!([]), Value::Array(vec![]));
+        assert_eq!(json!({}), parse("{}").unwrap());
+
+        let x = 2;
+        let key = "k".to_string();
+        let value = json!({
+            "a": [null, true, false, [1, {}], {"b": x * 2}, -0.5, "s",],
+            "nested": {"null": null, "empty": [], "expr": Some(x),},
+            (key): vec![x, x],
+            "last": {"c": [x]}
+        });
+        let expected = parse(
+            r#"{
+                "a": [null, true, false, [1, {}], {"b": 4}, -0.5, "s"],
+                "nested": {"null": null, "empty": [], "expr": 2},
+                "k": [2, 2],
+                "last": {"c": [2]}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+        // keyは記述した順に並ぶ
+        let keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["a", "nested", "k", "last"]);
+    }
+}
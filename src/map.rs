@@ -0,0 +1,409 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    iter::FromIterator,
+};
+
+use crate::Value;
+
+/// JSON Objectのkey-valueを保持する。keyの挿入順を保存する
+/// e.g. {"b": 1, "a": 2} は"b", "a"の順に並ぶ
+#[derive(Clone, Default)]
+pub struct Map {
+    /// 挿入順のkey-value
+    entries: Vec<(String, Value)>,
+    /// keyから`entries`の添字への対応
+    indices: HashMap<String, usize>,
+}
+
+impl Map {
+    /// 空の`Map`を返す
+    pub fn new() -> Map {
+        Map::default()
+    }
+
+    /// 要素数を返す
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 要素が空かどうかを返す
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// keyに対応する値を返す
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.indices.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    /// keyに対応する値を可変参照で返す
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self.indices.get(key) {
+            Some(&i) => Some(&mut self.entries[i].1),
+            None => None,
+        }
+    }
+
+    /// keyが存在するかどうかを返す
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// key-valueを挿入する。keyが既に存在する場合は位置を保ったまま値を置き換え、古い値を返す
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        match self.indices.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// keyに対応する`Entry`を返す。keyが存在しない場合は挿入できる
+    /// e.g. map.entry("count").or_insert(Value::Number(0.into()))
+    pub fn entry<S: Into<String>>(&mut self, key: S) -> Entry<'_> {
+        let key = key.into();
+        match self.indices.get(&key) {
+            Some(&index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// keyを削除して値を返す。残りの要素の順番は保たれる
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let i = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        // 削除した要素より後ろの添字を詰める
+        for (k, _) in &self.entries[i..] {
+            if let Some(index) = self.indices.get_mut(k) {
+                *index -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    /// keyの辞書順に並び替える
+    pub fn sort_keys(&mut self) {
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (i, (k, _)) in self.entries.iter().enumerate() {
+            if let Some(index) = self.indices.get_mut(k) {
+                *index = i;
+            }
+        }
+    }
+
+    /// 挿入順にkey-valueを返す
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+
+    /// 挿入順にkeyと値の可変参照を返す
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(self.entries.iter_mut())
+    }
+
+    /// 挿入順にkeyを返す
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    /// 挿入順に値を返す
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    /// 挿入順に値の可変参照を返す
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+}
+
+/// keyの順番は無視して比較する
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<(String, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl Extend<(String, Value)> for Map {
+    fn extend<I: IntoIterator<Item = (String, Value)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Map {
+    fn from(map: BTreeMap<String, Value>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+/// `Map::entry`が返す、存在するもしくは存在しないkeyの要素
+pub enum Entry<'a> {
+    /// keyが存在しない
+    Vacant(VacantEntry<'a>),
+    /// keyが存在する
+    Occupied(OccupiedEntry<'a>),
+}
+
+impl<'a> Entry<'a> {
+    /// keyを返す
+    pub fn key(&self) -> &str {
+        match self {
+            Entry::Vacant(entry) => entry.key(),
+            Entry::Occupied(entry) => entry.key(),
+        }
+    }
+
+    /// keyが存在しなければ`default`を挿入し、値の可変参照を返す
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        self.or_insert_with(|| default)
+    }
+
+    /// keyが存在しなければ`f`の戻り値を挿入し、値の可変参照を返す
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, f: F) -> &'a mut Value {
+        match self {
+            Entry::Vacant(entry) => entry.insert(f()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// keyが存在すれば値を`f`で変更する
+    pub fn and_modify<F: FnOnce(&mut Value)>(self, f: F) -> Entry<'a> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            entry => entry,
+        }
+    }
+}
+
+/// 存在しないkeyの`Entry`
+pub struct VacantEntry<'a> {
+    map: &'a mut Map,
+    key: String,
+}
+
+impl<'a> VacantEntry<'a> {
+    /// keyを返す
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 末尾に値を挿入し、その可変参照を返す
+    pub fn insert(self, value: Value) -> &'a mut Value {
+        let index = self.map.entries.len();
+        self.map.indices.insert(self.key.clone(), index);
+        self.map.entries.push((self.key, value));
+        &mut self.map.entries[index].1
+    }
+}
+
+/// 存在するkeyの`Entry`
+pub struct OccupiedEntry<'a> {
+    map: &'a mut Map,
+    /// `entries`の添字
+    index: usize,
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// keyを返す
+    pub fn key(&self) -> &str {
+        &self.map.entries[self.index].0
+    }
+
+    /// 値を返す
+    pub fn get(&self) -> &Value {
+        &self.map.entries[self.index].1
+    }
+
+    /// 値を可変参照で返す
+    pub fn get_mut(&mut self) -> &mut Value {
+        &mut self.map.entries[self.index].1
+    }
+
+    /// `Map`の借用期間の可変参照に変換する
+    pub fn into_mut(self) -> &'a mut Value {
+        &mut self.map.entries[self.index].1
+    }
+
+    /// 位置を保ったまま値を置き換え、古い値を返す
+    pub fn insert(&mut self, value: Value) -> Value {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// 要素を削除して値を返す。残りの要素の順番は保たれる
+    pub fn remove(self) -> Value {
+        let key = self.map.entries[self.index].0.clone();
+        self.map
+            .remove(&key)
+            .expect("an occupied entry always exists in the map")
+    }
+}
+
+/// `Map::iter`が返すイテレータ
+pub struct Iter<'a>(std::slice::Iter<'a, (String, Value)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a String, &'a Value);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k, v))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// `Map::iter_mut`が返すイテレータ
+pub struct IterMut<'a>(std::slice::IterMut<'a, (String, Value)>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a String, &'a mut Value);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (&*k, v))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+/// `Map`の`into_iter`が返すイテレータ
+pub struct IntoIter(std::vec::IntoIter<(String, Value)>);
+
+impl Iterator for IntoIter {
+    type Item = (String, Value);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl IntoIterator for Map {
+    type Item = (String, Value);
+    type IntoIter = IntoIter;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.entries.into_iter())
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a String, &'a Value);
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Map {
+    type Item = (&'a String, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(map: &Map) -> Vec<&str> {
+        map.keys().map(|k| k.as_str()).collect()
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = Map::new();
+        map.insert("b".to_string(), Value::Null);
+        map.insert("a".to_string(), Value::Bool(true));
+        map.insert("c".to_string(), Value::Bool(false));
+        assert_eq!(keys(&map), vec!["b", "a", "c"]);
+
+        // 既存のkeyは位置を保ったまま置き換える
+        assert_eq!(
+            map.insert("b".to_string(), Value::Bool(true)),
+            Some(Value::Null)
+        );
+        assert_eq!(keys(&map), vec!["b", "a", "c"]);
+        assert_eq!(map.get("b"), Some(&Value::Bool(true)));
+
+        assert_eq!(map.remove("b"), Some(Value::Bool(true)));
+        assert_eq!(map.remove("b"), None);
+        assert_eq!(keys(&map), vec!["a", "c"]);
+        assert_eq!(map.get("c"), Some(&Value::Bool(false)));
+
+        map.insert("b".to_string(), Value::Null);
+        map.sort_keys();
+        assert_eq!(keys(&map), vec!["a", "b", "c"]);
+        assert_eq!(map.get("b"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map = Map::new();
+        map.insert("a".to_string(), Value::Null);
+        assert_eq!(map.entry("a").key(), "a");
+        *map.entry("b").or_insert(Value::Bool(false)) = Value::Bool(true);
+        map.entry("a")
+            .and_modify(|v| *v = Value::Bool(false))
+            .or_insert(Value::Null);
+        map.entry("c").or_insert_with(|| Value::Array(vec![]));
+        assert_eq!(keys(&map), vec!["a", "b", "c"]);
+        assert_eq!(map.get("a"), Some(&Value::Bool(false)));
+        assert_eq!(map.get("b"), Some(&Value::Bool(true)));
+
+        match map.entry("a") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), Value::Bool(false)),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(keys(&map), vec!["b", "c"]);
+        assert_eq!(map.get("c"), Some(&Value::Array(vec![])));
+    }
+
+    #[test]
+    fn test_eq_ignores_order() {
+        let a = vec![("x", Value::Null), ("y", Value::Bool(true))]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<Map>();
+        let b = vec![("y", Value::Bool(true)), ("x", Value::Null)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<Map>();
+        assert_eq!(a, b);
+        assert_ne!(a, Map::new());
+    }
+}
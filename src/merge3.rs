@@ -0,0 +1,242 @@
+use std::fmt;
+
+use crate::{pointer::escape_token, Map, Value};
+
+/// 3-way mergeで両方が異なる変更をした箇所
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    path: String,
+    base: Option<Value>,
+    ours: Option<Value>,
+    theirs: Option<Value>,
+}
+
+impl Conflict {
+    /// 衝突した位置のJSON Pointer
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 共通の祖先の値。存在しない場合は`None`
+    pub fn base(&self) -> Option<&Value> {
+        self.base.as_ref()
+    }
+
+    /// 自分側の値。削除された場合は`None`
+    pub fn ours(&self) -> Option<&Value> {
+        self.ours.as_ref()
+    }
+
+    /// 相手側の値。削除された場合は`None`
+    pub fn theirs(&self) -> Option<&Value> {
+        self.theirs.as_ref()
+    }
+}
+
+/// e.g. /a: ours 1, theirs (deleted)
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |v: &Option<Value>| match v {
+            Some(v) => v.to_string(),
+            None => "(deleted)".to_string(),
+        };
+        write!(
+            f,
+            "{}: ours {}, theirs {}",
+            if self.path.is_empty() {
+                "(root)"
+            } else {
+                &self.path
+            },
+            side(&self.ours),
+            side(&self.theirs)
+        )
+    }
+}
+
+/// 3-way mergeで衝突が発生した場合のエラー
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    ours: Box<Value>,
+    theirs: Box<Value>,
+    conflicts: Vec<Conflict>,
+}
+
+impl MergeConflict {
+    /// 衝突した箇所の一覧
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// 衝突しなかった変更をマージし、衝突した箇所は自分側の値にした結果
+    pub fn ours(&self) -> &Value {
+        &self.ours
+    }
+
+    /// 衝突しなかった変更をマージし、衝突した箇所は相手側の値にした結果
+    pub fn theirs(&self) -> &Value {
+        &self.theirs
+    }
+}
+
+/// e.g. 2 conflicts: /a: ours 1, theirs 2; /b: ...
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} conflict(s): ", self.conflicts.len())?;
+        for (i, conflict) in self.conflicts.iter().enumerate() {
+            if i != 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MergeConflict {}
+
+/// 共通の祖先`base`から変更された`ours`と`theirs`を3-way mergeする
+/// - 片方だけが変更した値はその変更を採用する
+/// - 両方が`Object`に異なる変更をした場合はkeyごとに再帰的にマージする
+/// - それ以外で両方が異なる変更をした場合は衝突としてエラーを返す
+///
+/// `Array`は要素ごとにはマージしない。両方が同じ`Array`に変更した場合を除き、
+/// 両方が変更した`Array`はその全体が1つの衝突になる(e.g. 別々の位置に要素を追加した場合)
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> Result<Value, MergeConflict> {
+    let mut conflicts = vec![];
+    let merged = merge_value(&mut conflicts, "", Some(base), Some(ours), Some(theirs))
+        .expect("the root is never deleted");
+    if conflicts.is_empty() {
+        return Ok(merged);
+    }
+    // 衝突の親は両方に存在する`Object`なので、相手側の値に置き換えられる
+    let mut resolved = merged.clone();
+    for conflict in &conflicts {
+        let result = match &conflict.theirs {
+            Some(value) => resolved
+                .pointer_insert(&conflict.path, value.clone())
+                .map(|_| ()),
+            None => resolved.pointer_remove(&conflict.path).map(|_| ()),
+        };
+        result.expect("the parent of a conflict is an object");
+    }
+    Err(MergeConflict {
+        ours: Box::new(merged),
+        theirs: Box::new(resolved),
+        conflicts,
+    })
+}
+
+/// `None`は存在しない(削除された)値を表す。衝突した場合は自分側の値を返す
+fn merge_value(
+    conflicts: &mut Vec<Conflict>,
+    path: &str,
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+) -> Option<Value> {
+    if ours == theirs || base == theirs {
+        return ours.cloned();
+    }
+    if base == ours {
+        return theirs.cloned();
+    }
+    if let (Some(Value::Object(ours)), Some(Value::Object(theirs))) = (ours, theirs) {
+        let empty = Map::new();
+        let base = match base {
+            Some(Value::Object(base)) => base,
+            _ => &empty,
+        };
+        let keys = ours
+            .keys()
+            .chain(theirs.keys().filter(|k| !ours.contains_key(k)));
+        let mut merged = Map::new();
+        for key in keys {
+            let path = format!("{}/{}", path, escape_token(key));
+            let value = merge_value(
+                conflicts,
+                &path,
+                base.get(key),
+                ours.get(key),
+                theirs.get(key),
+            );
+            if let Some(value) = value {
+                merged.insert(key.clone(), value);
+            }
+        }
+        return Some(Value::Object(merged));
+    }
+    conflicts.push(Conflict {
+        path: path.to_string(),
+        base: base.cloned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    });
+    ours.cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_merge3() {
+        let base =
+            json!({"name": "app", "port": 80, "tags": ["a"], "debug": false, "db": {"host": "h"}});
+        let ours =
+            json!({"name": "app", "port": 8080, "tags": ["a"], "db": {"host": "h", "user": "u"}});
+        let theirs = json!({"name": "app2", "port": 80, "tags": ["a", "b"], "debug": false, "db": {"host": "h2"}, "new": 1});
+        let merged = merge3(&base, &ours, &theirs).unwrap();
+        assert_eq!(
+            merged,
+            json!({"name": "app2", "port": 8080, "tags": ["a", "b"], "db": {"host": "h2", "user": "u"}, "new": 1})
+        );
+        // keyの順番は自分側、相手側で追加されたkeyの順
+        let keys = merged.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["name", "port", "tags", "db", "new"]);
+
+        // 同じ変更は衝突しない
+        assert_eq!(merge3(&json!(1), &json!(2), &json!(2)), Ok(json!(2)));
+        // 両方が`Array`に同じ変更をした場合は衝突しない
+        assert_eq!(
+            merge3(
+                &json!({"a": [1], "b": 0}),
+                &json!({"a": [1, {"c": 2}], "b": 1}),
+                &json!({"a": [1, {"c": 2}], "b": 0})
+            ),
+            Ok(json!({"a": [1, {"c": 2}], "b": 1}))
+        );
+        // 両方が追加した`Object`はkeyごとにマージする
+        assert_eq!(
+            merge3(&json!({}), &json!({"a": {"x": 1}}), &json!({"a": {"y": 2}})),
+            Ok(json!({"a": {"x": 1, "y": 2}}))
+        );
+    }
+
+    #[test]
+    fn test_merge3_conflict() {
+        let base = json!({"a": 1, "b": [1], "c": {"d": true}, "e": "x"});
+        let ours = json!({"a": 2, "b": [1, 2], "c": {"d": false}, "e": "y"});
+        let theirs = json!({"a": 3, "b": [0, 1], "c": {}, "e": "y"});
+        let e = merge3(&base, &ours, &theirs).unwrap_err();
+        let paths = e.conflicts().iter().map(Conflict::path).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["/a", "/b", "/c/d"]);
+        assert_eq!(e.conflicts()[0].base(), Some(&json!(1)));
+        assert_eq!(e.conflicts()[2].ours(), Some(&json!(false)));
+        assert_eq!(e.conflicts()[2].theirs(), None);
+        assert_eq!(
+            e.ours(),
+            &json!({"a": 2, "b": [1, 2], "c": {"d": false}, "e": "y"})
+        );
+        assert_eq!(e.theirs(), &json!({"a": 3, "b": [0, 1], "c": {}, "e": "y"}));
+        assert_eq!(
+            e.to_string(),
+            "3 conflict(s): /a: ours 2, theirs 3; /b: ours [1,2], theirs [0,1]; /c/d: ours false, theirs (deleted)"
+        );
+
+        let e = merge3(&json!(1), &json!(2), &json!(3)).unwrap_err();
+        assert_eq!(e.conflicts()[0].path(), "");
+        assert_eq!(e.theirs(), &json!(3));
+    }
+}
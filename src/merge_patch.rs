@@ -0,0 +1,157 @@
+use crate::{Map, Value};
+
+impl Value {
+    /// JSON Merge Patch(RFC 7396)を適用する
+    /// - `patch`が`Object`なら再帰的にマージし、値が`null`のkeyは削除する
+    /// - それ以外なら`patch`で置き換える
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc7396#section-2
+    pub fn merge_patch(&mut self, patch: &Value) {
+        let patch = match patch {
+            Value::Object(patch) => patch,
+            patch => {
+                *self = patch.clone();
+                return;
+            }
+        };
+        if !self.is_object() {
+            *self = Value::Object(Map::new());
+        }
+        if let Value::Object(map) = self {
+            for (key, value) in patch {
+                if value.is_null() {
+                    map.remove(key);
+                } else {
+                    map.entry(key.as_str())
+                        .or_insert(Value::Null)
+                        .merge_patch(value);
+                }
+            }
+        }
+    }
+}
+
+/// `from`に適用すると`to`になるJSON Merge Patch(RFC 7396)を返す
+/// JSON Merge Patchは`null`の値を表現できないので、`to`の`Object`が
+/// `null`の値を含む場合は適用しても`to`にならない
+pub fn create_merge_patch(from: &Value, to: &Value) -> Value {
+    let (from, to) = match (from, to) {
+        (Value::Object(from), Value::Object(to)) => (from, to),
+        (_, to) => return to.clone(),
+    };
+    let mut patch = Map::new();
+    for (key, value) in to {
+        match from.get(key) {
+            Some(old) if old == value => {}
+            Some(old) => {
+                patch.insert(key.clone(), create_merge_patch(old, value));
+            }
+            None => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    for key in from.keys() {
+        if !to.contains_key(key) {
+            patch.insert(key.clone(), Value::Null);
+        }
+    }
+    Value::Object(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_merge_patch() {
+        // https://www.rfc-editor.org/rfc/rfc7396#appendix-A
+        let cases = vec![
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+        for (mut target, patch, expected) in cases {
+            target.merge_patch(&patch);
+            assert_eq!(target, expected, "{}", patch);
+        }
+    }
+
+    #[test]
+    fn test_create_merge_patch() {
+        let from = json!({
+            "title": "Goodbye!",
+            "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged"
+        });
+        let to = json!({
+            "title": "Hello!",
+            "author": {"givenName": "John"},
+            "tags": ["example"],
+            "content": "This will be unchanged",
+            "phoneNumber": "+01-123-456-7890"
+        });
+        let patch = create_merge_patch(&from, &to);
+        assert_eq!(
+            patch,
+            json!({
+                "title": "Hello!",
+                "author": {"familyName": null},
+                "tags": ["example"],
+                "phoneNumber": "+01-123-456-7890"
+            })
+        );
+        let mut patched = from.clone();
+        patched.merge_patch(&patch);
+        assert_eq!(patched, to);
+
+        assert_eq!(create_merge_patch(&from, &from), json!({}));
+        assert_eq!(create_merge_patch(&from, &json!([1])), json!([1]));
+        assert_eq!(
+            create_merge_patch(&json!(1), &json!({"a": 1})),
+            json!({"a": 1})
+        );
+    }
+}
@@ -0,0 +1,584 @@
+use std::{fmt, str::FromStr};
+
+use crate::{lexer::is_strict_number, Error, ErrorKind, Position};
+
+/// JSONの数値
+/// 整数のリテラルは`i64`/`u64`のまま保持し、それ以外は`f64`として保持する
+/// e.g. 9007199254740993 (2^53 + 1) も正確に保持できる
+/// `ParseOptions::preserve_number_literals`が有効な場合は入力のリテラルをそのまま保持する
+/// e.g. 1.10 や 40桁の整数
+#[derive(Clone)]
+pub struct Number {
+    n: N,
+}
+
+#[derive(Debug, Clone)]
+enum N {
+    /// 0以上の整数
+    PosInt(u64),
+    /// 負の整数(常に0未満)
+    NegInt(i64),
+    /// 浮動小数点数
+    Float(f64),
+    /// RFC8259の文法に従う入力のリテラル
+    Literal(Box<str>),
+}
+
+/// `u64`の上限(2^64)
+const U64_UPPER: f64 = 18_446_744_073_709_551_616.0;
+/// `i64`の上限(2^63)
+const I64_UPPER: f64 = 9_223_372_036_854_775_808.0;
+
+impl Number {
+    /// 数値のリテラルから`Number`を返す。整数のリテラルで`i64`/`u64`に収まるなら整数として保持する
+    /// e.g. "123" => 整数, "1.5" "1e3" => `f64`
+    pub(crate) fn from_literal(s: &str) -> Option<Number> {
+        if is_integer_literal(s) {
+            if s.starts_with('-') {
+                match s.parse::<i64>() {
+                    // "-0"は符号を保つために`f64`として保持する
+                    Ok(0) => {}
+                    Ok(i) => return Some(Number::from(i)),
+                    Err(_) => {}
+                }
+            } else if let Ok(u) = s.parse::<u64>() {
+                return Some(Number::from(u));
+            }
+        }
+        // 整数に収まらない場合は`f64`として扱う
+        s.parse::<f64>().ok().map(Number::from)
+    }
+
+    /// 数値のリテラルをそのまま保持する`Number`を返す。RFC8259の文法に従わない場合は通常の数値として扱う
+    /// e.g. "1.10" => "1.10", "+1" => 1
+    pub(crate) fn from_literal_preserved(s: &str) -> Option<Number> {
+        if is_strict_number(s) {
+            Some(Number {
+                n: N::Literal(s.into()),
+            })
+        } else {
+            Number::from_literal(s)
+        }
+    }
+
+    /// 保持しているリテラルを返す。リテラルを保持していない場合は`None`を返す
+    pub fn as_literal(&self) -> Option<&str> {
+        match &self.n {
+            N::Literal(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// `i64`として表現できるかどうかを返す
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// `u64`として表現できるかどうかを返す
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    /// 浮動小数点数として保持しているかどうかを返す。リテラルの場合は小数部もしくは指数部を持つかどうかを返す
+    pub fn is_f64(&self) -> bool {
+        match &self.n {
+            N::Float(_) => true,
+            N::Literal(s) => !is_integer_literal(s),
+            _ => false,
+        }
+    }
+
+    /// `i64`に変換する。範囲外や小数部を持つなど、値が変わってしまう場合は`None`を返す
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.n {
+            N::Literal(ref s) => Number::from_literal(s)?.as_i64(),
+            N::PosInt(u) if u <= i64::MAX as u64 => Some(u as i64),
+            N::PosInt(_) => None,
+            N::NegInt(i) => Some(i),
+            N::Float(f) if f.fract() == 0.0 && (-I64_UPPER..I64_UPPER).contains(&f) => {
+                Some(f as i64)
+            }
+            N::Float(_) => None,
+        }
+    }
+
+    /// `u64`に変換する。負数や小数部を持つなど、値が変わってしまう場合は`None`を返す
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.n {
+            N::Literal(ref s) => Number::from_literal(s)?.as_u64(),
+            N::PosInt(u) => Some(u),
+            N::NegInt(_) => None,
+            N::Float(f) if f.fract() == 0.0 && (0.0..U64_UPPER).contains(&f) => Some(f as u64),
+            N::Float(_) => None,
+        }
+    }
+
+    /// `f64`に変換する。2^53を超える整数など、`f64`で正確に表現できない場合は`None`を返す
+    /// 小数のリテラルは最も近い`f64`を返す
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.n {
+            N::Literal(ref s) => {
+                let n = Number::from_literal(s)?;
+                // `u64`/`i64`に収まらない整数のリテラルは`f64`でも正確に表現できない
+                if n.is_f64() && is_integer_literal(s) {
+                    None
+                } else {
+                    n.as_f64()
+                }
+            }
+            N::PosInt(u) => {
+                let f = u as f64;
+                if f < U64_UPPER && f as u64 == u {
+                    Some(f)
+                } else {
+                    None
+                }
+            }
+            N::NegInt(i) => {
+                let f = i as f64;
+                if f as i64 == i {
+                    Some(f)
+                } else {
+                    None
+                }
+            }
+            N::Float(f) => Some(f),
+        }
+    }
+
+    /// `f64`に変換する。精度が落ちる場合は最も近い値を返す
+    pub fn to_f64_lossy(&self) -> f64 {
+        match self.n {
+            N::PosInt(u) => u as f64,
+            N::NegInt(i) => i as f64,
+            N::Float(f) => f,
+            N::Literal(ref s) => s.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// 10進数として正確な値を返す。NaNやInfinityの場合は`None`を返す
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        match self.n {
+            N::Literal(ref s) => s.parse().ok(),
+            N::Float(f) if !f.is_finite() => None,
+            // `f64`の`Display`は指数表記を使わない
+            _ => self.to_string().parse().ok(),
+        }
+    }
+
+    /// JSONとして出力できる値かどうかを返す(NaNやInfinityは出力できない)
+    pub(crate) fn is_finite(&self) -> bool {
+        match self.n {
+            N::Float(f) => f.is_finite(),
+            _ => true,
+        }
+    }
+}
+
+/// 小数部と指数部を持たないリテラルかどうかを返す
+fn is_integer_literal(s: &str) -> bool {
+    !s.contains(['.', 'e', 'E'].as_ref())
+}
+
+/// RFC8259の文法に従う数値のリテラルをそのまま保持する`Number`に変換する
+/// e.g. `"1.10".parse::<Number>()`
+impl FromStr for Number {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if is_strict_number(s) {
+            Ok(Number {
+                n: N::Literal(s.into()),
+            })
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidNumber,
+                &format!("a number is expected, but found {:?}", s),
+                Position::start(),
+            ))
+        }
+    }
+}
+
+/// 整数と浮動小数点数は値が等しければ等しいとみなす
+/// e.g. `Number::from(1) == Number::from(1.0)`
+/// リテラルは10進数として比較する
+/// e.g. 1.10 == 1.1
+/// 指数部が大きすぎて10進数として表せないリテラルは`f64`として比較する
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.n, &other.n) {
+            (N::Literal(_), _) | (_, N::Literal(_)) => {
+                match (self.to_decimal(), other.to_decimal()) {
+                    (Some(a), Some(b)) => a == b,
+                    (None, _) | (_, None) if self.is_finite() && other.is_finite() => {
+                        self.to_f64_lossy() == other.to_f64_lossy()
+                    }
+                    _ => false,
+                }
+            }
+            (N::PosInt(a), N::PosInt(b)) => a == b,
+            (N::NegInt(a), N::NegInt(b)) => a == b,
+            (N::Float(a), N::Float(b)) => a == b,
+            (N::Float(f), _) => other.as_f64() == Some(*f),
+            (_, N::Float(f)) => self.as_f64() == Some(*f),
+            // 0以上の整数と負の整数
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.n {
+            N::PosInt(u) => write!(f, "{}", u),
+            N::NegInt(i) => write!(f, "{}", i),
+            N::Float(v) => write!(f, "{}", v),
+            N::Literal(ref s) => f.write_str(s),
+        }
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.n {
+            N::Float(v) => write!(f, "Number({:?})", v),
+            _ => write!(f, "Number({})", self),
+        }
+    }
+}
+
+/// `Decimal`を指数表記を使わずに出力する場合に補う0の桁数の上限
+const MAX_DISPLAY_ZEROS: u64 = 1000;
+
+/// 10進数の数値。`digits * 10^(-scale)`を表す
+/// e.g. 1.10 => digits: "110", scale: 2
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    /// 負数かどうか
+    negative: bool,
+    /// 先頭に0を含まない10進数の数字列(0の場合は"0")
+    digits: String,
+    /// 小数点以下の桁数。負の場合は10の累乗を掛ける
+    scale: i64,
+}
+
+impl Decimal {
+    /// 負数かどうかを返す
+    pub fn is_negative(&self) -> bool {
+        self.negative && self.digits != "0"
+    }
+
+    /// 符号と小数点を除いた数字列を返す
+    /// e.g. -1.10 => "110"
+    pub fn digits(&self) -> &str {
+        &self.digits
+    }
+
+    /// 小数点以下の桁数を返す
+    /// e.g. 1.10 => 2, 1e3 => -3
+    pub fn scale(&self) -> i64 {
+        self.scale
+    }
+
+    /// 末尾の0を取り除いた(`digits`, `scale`)を返す
+    fn normalized(&self) -> (&str, i64) {
+        let digits = self.digits.trim_end_matches('0');
+        if digits.is_empty() {
+            return ("0", 0);
+        }
+        let zeros = (self.digits.len() - digits.len()) as i64;
+        (digits, self.scale - zeros)
+    }
+}
+
+/// 数値のリテラル(RFC8259の文法)から`Decimal`を返す
+/// e.g. "-1.10e1" => -11.0
+impl FromStr for Decimal {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !is_strict_number(s) {
+            return Err(Error::new(
+                ErrorKind::InvalidNumber,
+                &format!("a number is expected, but found {:?}", s),
+                Position::start(),
+            ));
+        }
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (mantissa, exponent) = match s.find(['e', 'E'].as_ref()) {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, "0"),
+        };
+        let (int, frac) = match mantissa.find('.') {
+            Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+            None => (mantissa, ""),
+        };
+        // 指数部が`i64`に収まらない場合はエラー
+        let exponent = exponent.parse::<i64>().map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidNumber,
+                &format!("an exponent is too large {:?} ({})", exponent, e),
+                Position::start(),
+            )
+        })?;
+        let digits = format!("{}{}", int, frac);
+        let digits = match digits.trim_start_matches('0') {
+            "" => "0".to_string(),
+            digits => digits.to_string(),
+        };
+        // `scale`と、末尾の0を取り除いた場合の`scale`が`i64`に収まらない場合はエラー
+        // e.g. 1.5e-9223372036854775807
+        let scale = (frac.len() as i64)
+            .checked_sub(exponent)
+            .filter(|scale| scale.checked_sub(digits.len() as i64).is_some())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidNumber,
+                    &format!("an exponent is out of range {:?}", exponent),
+                    Position::start(),
+                )
+            })?;
+        Ok(Decimal {
+            negative,
+            digits,
+            scale,
+        })
+    }
+}
+
+/// 数学的に等しければ等しいとみなす
+/// e.g. 1.10 == 1.1
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_negative() == other.is_negative() && self.normalized() == other.normalized()
+    }
+}
+
+/// 指数表記を使わずに出力する。ただし補う0が`MAX_DISPLAY_ZEROS`桁を超える場合は指数表記にする
+/// e.g. 1.10 => "1.10", 1e3 => "1000", 1e1000 => "1e1000"
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_negative() {
+            f.write_str("-")?;
+        }
+        // 補う0の桁数 (e.g. 1e3 => 3, 0.0015 => 3)
+        let zeros = if self.scale <= 0 {
+            self.scale.unsigned_abs()
+        } else {
+            self.scale
+                .unsigned_abs()
+                .saturating_sub(self.digits.len() as u64)
+        };
+        if self.digits != "0" && zeros > MAX_DISPLAY_ZEROS {
+            return write!(f, "{}e{}", self.digits, -(self.scale as i128));
+        }
+        if self.scale <= 0 {
+            f.write_str(&self.digits)?;
+            if self.digits != "0" {
+                write!(f, "{:0<width$}", "", width = (-self.scale) as usize)?;
+            }
+            Ok(())
+        } else {
+            let scale = self.scale as usize;
+            if self.digits.len() > scale {
+                let (int, frac) = self.digits.split_at(self.digits.len() - scale);
+                write!(f, "{}.{}", int, frac)
+            } else {
+                write!(f, "0.{:0>width$}", self.digits, width = scale)
+            }
+        }
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(u: $ty) -> Self {
+                    Number { n: N::PosInt(u as u64) }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_from_signed {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(i: $ty) -> Self {
+                    if i < 0 {
+                        Number { n: N::NegInt(i as i64) }
+                    } else {
+                        Number { n: N::PosInt(i as u64) }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_unsigned!(u8, u16, u32, u64, usize);
+impl_from_signed!(i8, i16, i32, i64, isize);
+
+impl From<f64> for Number {
+    fn from(f: f64) -> Self {
+        Number { n: N::Float(f) }
+    }
+}
+
+impl From<f32> for Number {
+    fn from(f: f32) -> Self {
+        Number {
+            n: N::Float(f as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_literal() {
+        let n = Number::from_literal("9007199254740993").unwrap();
+        assert_eq!(n.as_u64(), Some(9_007_199_254_740_993));
+        assert_eq!(n.as_i64(), Some(9_007_199_254_740_993));
+        // f64では正確に表現できない
+        assert_eq!(n.as_f64(), None);
+        assert_eq!(n.to_string(), "9007199254740993");
+
+        let n = Number::from_literal("18446744073709551615").unwrap();
+        assert_eq!(n.as_u64(), Some(u64::MAX));
+        assert_eq!(n.as_i64(), None);
+        assert_eq!(n.as_f64(), None);
+
+        let n = Number::from_literal("-9223372036854775808").unwrap();
+        assert_eq!(n.as_i64(), Some(i64::MIN));
+        assert_eq!(n.as_u64(), None);
+        assert_eq!(n.as_f64(), Some(-9_223_372_036_854_775_808.0));
+
+        // 整数に収まらない場合は`f64`
+        let n = Number::from_literal("18446744073709551616").unwrap();
+        assert!(n.is_f64());
+        assert_eq!(n.as_u64(), None);
+
+        let n = Number::from_literal("-0").unwrap();
+        assert!(n.is_f64());
+        assert!(n.as_f64().unwrap().is_sign_negative());
+
+        let n = Number::from_literal("1.5e1").unwrap();
+        assert!(n.is_f64());
+        assert_eq!(n.as_i64(), Some(15));
+        assert_eq!(Number::from_literal("1.5").unwrap().as_i64(), None);
+
+        assert!(Number::from_literal("1e").is_none());
+    }
+
+    #[test]
+    fn test_eq() {
+        assert_eq!(Number::from(1), Number::from(1.0));
+        assert_eq!(Number::from(-1i64), Number::from(-1.0));
+        assert_ne!(Number::from(1), Number::from(1.5));
+        assert_ne!(Number::from(u64::MAX), Number::from(u64::MAX as f64));
+        assert_ne!(Number::from(1), Number::from(-1));
+    }
+
+    #[test]
+    fn test_literal() {
+        let n = Number::from_literal_preserved("1.10").unwrap();
+        assert_eq!(n.to_string(), "1.10");
+        assert_eq!(n.as_literal(), Some("1.10"));
+        assert_eq!(n.as_f64(), Some(1.1));
+        assert_eq!(n, Number::from(1.1));
+        assert!(n.is_f64());
+
+        let literal = "1234567890123456789012345678901234567890";
+        let n = Number::from_literal_preserved(literal).unwrap();
+        assert_eq!(n.to_string(), literal);
+        assert!(!n.is_f64());
+        assert_eq!(n.as_u64(), None);
+        assert_eq!(n.as_f64(), None);
+        assert_eq!(n.to_decimal().unwrap().digits(), literal);
+
+        let n = Number::from_literal_preserved("-12").unwrap();
+        assert_eq!(n.as_i64(), Some(-12));
+        assert_eq!(n, Number::from(-12));
+
+        // RFC8259の文法に従わないリテラルは保持しない
+        let n = Number::from_literal_preserved("+1").unwrap();
+        assert_eq!(n.as_literal(), None);
+        assert_eq!(n.to_string(), "1");
+
+        assert_eq!("1e2".parse::<Number>().unwrap().to_string(), "1e2");
+        assert!("+1".parse::<Number>().is_err());
+    }
+
+    #[test]
+    fn test_decimal() {
+        let cases = [
+            ("1.10", "1.10", "110", 2),
+            ("-0.005", "-0.005", "5", 3),
+            ("1e3", "1000", "1", -3),
+            ("1.5E-3", "0.0015", "15", 4),
+            ("-0", "0", "0", 0),
+            ("120", "120", "120", 0),
+        ];
+        for (literal, display, digits, scale) in cases {
+            let d = literal.parse::<Decimal>().unwrap();
+            assert_eq!(d.to_string(), display, "{}", literal);
+            assert_eq!(d.digits(), digits, "{}", literal);
+            assert_eq!(d.scale(), scale, "{}", literal);
+        }
+        assert_eq!(
+            "1.10".parse::<Decimal>().unwrap(),
+            "0.11e1".parse::<Decimal>().unwrap()
+        );
+        assert_ne!(
+            "1.10".parse::<Decimal>().unwrap(),
+            "-1.1".parse::<Decimal>().unwrap()
+        );
+        assert_eq!(
+            Number::from(0.1).to_decimal(),
+            "0.1".parse::<Decimal>().ok()
+        );
+        assert_eq!(Number::from(f64::NAN).to_decimal(), None);
+    }
+
+    #[test]
+    fn test_decimal_extreme_exponent() {
+        // `scale`が`i64`に収まらない指数部はエラー
+        for literal in [
+            "1.5e-9223372036854775807",
+            "1e-9223372036854775808",
+            "1e9223372036854775808",
+        ] {
+            let e = literal.parse::<Decimal>().unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::InvalidNumber, "{}", literal);
+            // 比較してもpanicしない
+            let n = literal.parse::<Number>().unwrap();
+            assert_eq!(n, n.clone());
+        }
+        assert_ne!(
+            "1e-9223372036854775808".parse::<Number>().unwrap(),
+            Number::from(1)
+        );
+        let options = crate::ParseOptions {
+            preserve_number_literals: true,
+            ..crate::ParseOptions::default()
+        };
+        let v = crate::parse_with("[1e-9223372036854775808, 1e9223372036854775807]", options);
+        let v = v.unwrap();
+        assert_eq!(v, v.clone());
+
+        // 大きな指数部でも出力は指数表記になり、大量の0を書き出さない
+        let d = "1.5e9223372036854775807".parse::<Decimal>().unwrap();
+        assert_eq!(d.to_string(), "15e9223372036854775806");
+        let d = "-1.5e-9223372036854775000".parse::<Decimal>().unwrap();
+        assert_eq!(d.to_string(), "-15e-9223372036854775001");
+        assert_eq!("1e1000".parse::<Decimal>().unwrap().to_string().len(), 1001);
+        assert_eq!("1e1001".parse::<Decimal>().unwrap().to_string(), "1e1001");
+    }
+}
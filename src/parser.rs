@@ -1,235 +1,190 @@
-use crate::{lexer::Token, Value};
+use std::collections::HashMap;
 
-#[derive(Debug, Clone)]
-pub struct ParserError {
-    pub msg: String,
-}
+use crate::{
+    event::{Event, EventReader, TokenSource},
+    lexer::{Position, Span},
+    DuplicateKeys, Error, ErrorKind, Map, ParseOptions, Value,
+};
+#[cfg(test)]
+use crate::{lexer::Lexer, source::StrSource};
 
-impl ParserError {
-    pub fn new(msg: &str) -> ParserError {
-        ParserError {
-            msg: msg.to_string(),
-        }
-    }
+/// `EventReader`のイベントから`Value`を組み立てる
+pub struct Parser<'a> {
+    // イベントの入力元
+    events: EventReader<'a>,
+    // `Object`のkeyが重複した場合の扱い
+    duplicate_keys: DuplicateKeys,
 }
-pub struct Parser {
-    // `Lexer`で`tokenize`した`Token`一覧
-    tokens: Vec<Token>,
-    // `tokens`の先頭
-    index: usize,
+
+/// 組み立て中のコンテナ
+enum Frame {
+    Array(Vec<Value>),
+    /// 値を待っているkeyと、`DuplicateKeys::Error`の場合に使うkeyの出現位置
+    Object(Map, Option<String>, HashMap<String, Position>),
 }
 
-impl Parser {
-    /// `Token`の一覧を受け取り`Parser`を返す。
-    pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, index: 0 }
+impl<'a> Parser<'a> {
+    /// `Lexer`を受け取り`Parser`を返す。
+    #[cfg(test)]
+    pub fn new(lexer: Lexer<StrSource<'a>>) -> Parser<'a> {
+        Parser::with_options(lexer, ParseOptions::default())
     }
 
-    /// `Array`のパースを行う。
-    ///  [1, 2, 3, null, "string"]
-    fn parse_array(&mut self) -> Result<Value, ParserError> {
-        // 先頭は必ず [
-        let token = self.peek_expect()?;
-        if *token != Token::LeftBracket {
-            return Err(ParserError::new(&format!(
-                "error: JSON array must starts [ {:?}",
-                token
-            )));
-        }
-        // [ を読み飛ばす
-        self.next_expect()?;
-
-        let mut array = vec![];
-        let token = self.peek_expect()?;
-        // ] なら空配列を返す
-        if *token == Token::RightBracket {
-            // ] を読み飛ばす
-            self.next_expect()?;
-            return Ok(Value::Array(array));
+    /// `Token`の入力元とパースのオプションを受け取り`Parser`を返す。
+    pub fn with_options<T: TokenSource + 'a>(tokens: T, options: ParseOptions) -> Parser<'a> {
+        Parser::from_events(EventReader::from_tokens(tokens, options), options)
+    }
+
+    /// `EventReader`とパースのオプションを受け取り`Parser`を返す。
+    pub fn from_events(events: EventReader<'a>, options: ParseOptions) -> Parser<'a> {
+        Parser {
+            events,
+            duplicate_keys: options.duplicate_keys,
         }
+    }
 
+    /// JSON全体をパースする。Strictモードでは値の後ろに`Token`が残っている場合はエラーを返す。
+    /// e.g. [1] garbage
+    pub fn parse_document(&mut self) -> Result<Value, Error> {
+        let value = self.parse()?;
+        // 値の後ろのイベントは存在しない(Strictモードの場合は`EventReader`がエラーを返す)
+        self.events.next_event()?;
+        Ok(value)
+    }
+
+    /// 値を一つ分のイベントを読み込んで`Value`に変換する。
+    pub fn parse(&mut self) -> Result<Value, Error> {
+        let mut builder = ValueBuilder::new(self.duplicate_keys);
         loop {
-            // 残りの`Value`をパースする
-            let value = self.parse()?;
-            array.push(value);
-
-            // `Array`が終端もしくは次の要素(`Value`)があるかを確認
-            let token = self.next_expect()?;
-            match token {
-                // ] は`Array`の終端
-                Token::RightBracket => {
-                    return Ok(Value::Array(array));
-                }
-                // , なら次の要素(`Value`)をパースする
-                Token::Comma => {
-                    continue;
-                }
-                _ => {
-                    return Err(ParserError::new(&format!(
-                        "error: a [ or , token is expected {:?}",
-                        token
-                    )));
+            let (event, span) = match self.events.next_event()? {
+                Some(event) => event,
+                // 値を読み終えた後に呼ばれた場合
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "a value is expected, but found the end of input",
+                        self.events.end(),
+                    ))
                 }
+            };
+            if let Some(value) = builder.push(event, span)? {
+                return Ok(value);
             }
         }
     }
+}
 
-    /// `Object`のパースを行う。
-    /// {
-    ///   "key1": 12345,
-    ///   "key2": 6789
-    /// }
-    fn parse_object(&mut self) -> Result<Value, ParserError> {
-        // 先頭は必ず {
-        let token = self.peek_expect()?;
-        if *token != Token::LeftBrace {
-            return Err(ParserError::new(&format!(
-                "error: JSON object must starts {{ {:?}",
-                token
-            )));
-        }
-        // { を読み飛ばす
-        self.next_expect()?;
-
-        let mut object = std::collections::BTreeMap::new();
+/// イベントを一つずつ受け取り`Value`を組み立てる。
+/// ネストしたコンテナは再帰呼び出しではなくスタックで組み立てる。
+/// {
+///   "key1": [1, 2],
+///   "key2": 6789
+/// }
+pub(crate) struct ValueBuilder {
+    // `Object`のkeyが重複した場合の扱い
+    duplicate_keys: DuplicateKeys,
+    // 組み立て中のコンテナ
+    stack: Vec<Frame>,
+}
 
-        // } なら空の`Object`を返す
-        if *self.peek_expect()? == Token::RightBrace {
-            // } を読み飛ばす
-            self.next_expect()?;
-            return Ok(Value::Object(object));
-        }
-
-        loop {
-            // 2文字分の`Token`を読み出す
-            let token1 = self.next_expect()?.clone();
-            let token2 = self.next_expect()?;
-
-            match (token1, token2) {
-                // token1とtoken2はそれぞれ"key"(`Token::String`)と:(`Token::Colon`)であることを想定してる
-                // e.g. "key" : 12345
-                // "key" (`Token::String`)
-                // : (`Token::Colon`)
-                (Token::String(key), Token::Colon) => {
-                    // 残りの`Value`(12345)をパースする。
-                    object.insert(key, self.parse()?);
-                }
-                // それ以外はエラー
-                _ => {
-                    return Err(ParserError::new(
-                        "error: a pair (key(string) and : token) token is expected",
-                    ));
-                }
-            }
-            // `Object`が終端かもしくは次の要素(key-value)があるか
-            let token3 = self.next_expect()?;
-            match token3 {
-                // } `Object`の終端だったら`object`を返す
-                Token::RightBrace => {
-                    return Ok(Value::Object(object));
-                }
-                // , なら次の要素(key-value)のパースする
-                // {
-                //    "key1": 12345,
-                //    "key2": 6789
-                // }
-                Token::Comma => {
-                    continue;
-                }
-                _ => {
-                    return Err(ParserError::new(&format!(
-                        "error: a {{ or , token is expected {:?}",
-                        token3
-                    )));
-                }
-            }
+impl ValueBuilder {
+    pub(crate) fn new(duplicate_keys: DuplicateKeys) -> ValueBuilder {
+        ValueBuilder {
+            duplicate_keys,
+            stack: vec![],
         }
     }
 
-    /// `Token`を評価して`Value`に変換する。この関数は再帰的に呼び出される。
-    pub fn parse(&mut self) -> Result<Value, ParserError> {
-        let token = self.peek_expect()?.clone();
-        let value = match token {
-            // { はObjectの開始文字
-            Token::LeftBrace => self.parse_object(),
-            // [ はArrayの開始文字
-            Token::LeftBracket => self.parse_array(),
-            Token::String(s) => {
-                self.next_expect()?;
-                Ok(Value::String(s))
-            }
-            Token::Number(n) => {
-                self.next_expect()?;
-                Ok(Value::Number(n))
-            }
-            Token::Bool(b) => {
-                self.next_expect()?;
-                Ok(Value::Bool(b))
+    /// イベントを一つ受け取る。値全体を組み立て終えた場合はその値を返す
+    pub(crate) fn push(&mut self, event: Event, span: Span) -> Result<Option<Value>, Error> {
+        let value = match event {
+            // コンテナの開始はスタックに積む
+            Event::StartArray => {
+                self.stack.push(Frame::Array(vec![]));
+                return Ok(None);
             }
-            Token::Null => {
-                self.next_expect()?;
-                Ok(Value::Null)
+            Event::StartObject => {
+                self.stack
+                    .push(Frame::Object(Map::new(), None, HashMap::new()));
+                return Ok(None);
             }
-            _ => {
-                return Err(ParserError::new(&format!(
-                    "error: a token must start {{ or [ or string or number or bool or null {:?}",
-                    token
-                )))
+            // keyは対応する値が来るまで保持する
+            Event::Key(key) => {
+                if let Some(Frame::Object(_, pending, key_positions)) = self.stack.last_mut() {
+                    // keyの重複を確認する
+                    if self.duplicate_keys == DuplicateKeys::Error {
+                        if let Some(&first_position) = key_positions.get(&key) {
+                            return Err(Error::new(
+                                ErrorKind::DuplicateKey,
+                                &format!(
+                                    "a duplicate key {:?} is found (the first occurrence is at {})",
+                                    key, first_position
+                                ),
+                                span.start,
+                            )
+                            .with_related_position(first_position));
+                        }
+                        key_positions.insert(key.clone(), span.start);
+                    }
+                    *pending = Some(key);
+                }
+                return Ok(None);
             }
+            // コンテナの終了はスタックから取り出して値にする
+            Event::EndArray | Event::EndObject => match self.stack.pop() {
+                Some(Frame::Array(array)) => Value::Array(array),
+                Some(Frame::Object(object, _, _)) => Value::Object(object),
+                None => unreachable!("the grammar never produces an unbalanced end"),
+            },
+            Event::String(s) => Value::String(s),
+            Event::Number(n) => Value::Number(n),
+            Event::Bool(b) => Value::Bool(b),
+            Event::Null => Value::Null,
         };
-        value
-    }
-    /// 先頭の`Token`を返す。
-    fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.index)
-    }
-
-    /// 先頭の`Token`を返す。(先頭に`Token`があることを想定してる)
-    fn peek_expect(&self) -> Result<&Token, ParserError> {
-        self.peek()
-            .ok_or_else(|| ParserError::new("error: a token isn't peekable"))
-    }
 
-    /// 先頭の`Token`を返して、1トークン進める。
-    fn next(&mut self) -> Option<&Token> {
-        self.index += 1;
-        self.tokens.get(self.index - 1)
-    }
-
-    /// 先頭の`Token`を返して、1トークン進める。(先頭に`Token`があることを想定してる)
-    fn next_expect(&mut self) -> Result<&Token, ParserError> {
-        self.next()
-            .ok_or_else(|| ParserError::new("error: a token isn't peekable"))
+        // 読み込んだ値を親のコンテナに追加する。親がなければ値全体を読み終えた
+        match self.stack.last_mut() {
+            None => Ok(Some(value)),
+            Some(Frame::Array(array)) => {
+                array.push(value);
+                Ok(None)
+            }
+            Some(Frame::Object(object, pending, _)) => {
+                let key = pending.take().expect("a key precedes a value in an object");
+                // `DuplicateKeys::FirstWins`の場合は最初の値を残す
+                if self.duplicate_keys != DuplicateKeys::FirstWins || !object.contains_key(&key) {
+                    object.insert(key, value);
+                }
+                Ok(None)
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::collections::BTreeMap;
-
-    use crate::{lexer::Lexer, Value};
+    use crate::{
+        lexer::{Lexer, Position},
+        DuplicateKeys, ErrorKind, Map, ParseOptions, Value,
+    };
 
     use super::Parser;
 
     #[test]
     fn test_parse_empty_array() {
         let json = r#"{"a": [], "b": 1}"#;
-        let value = Parser::new(Lexer::new(json).tokenize().unwrap())
-            .parse()
-            .unwrap();
-        let mut object = BTreeMap::new();
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
+        let mut object = Map::new();
         object.insert("a".to_string(), Value::Array(vec![]));
-        object.insert("b".to_string(), Value::Number(1.0));
+        object.insert("b".to_string(), Value::Number(1.into()));
         assert_eq!(value, Value::Object(object));
     }
 
     #[test]
     fn test_parse_object() {
         let json = r#"{"togatoga" : "monkey-json"}"#;
-        let value = Parser::new(Lexer::new(json).tokenize().unwrap())
-            .parse()
-            .unwrap();
-        let mut object = BTreeMap::new();
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
+        let mut object = Map::new();
         object.insert(
             "togatoga".to_string(),
             Value::String("monkey-json".to_string()),
@@ -244,11 +199,9 @@ mod test {
         }
         "#;
 
-        let value = Parser::new(Lexer::new(json).tokenize().unwrap())
-            .parse()
-            .unwrap();
-        let mut object = BTreeMap::new();
-        let mut nested_object = BTreeMap::new();
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
+        let mut object = Map::new();
+        let mut nested_object = Map::new();
         nested_object.insert("key".to_string(), Value::Bool(false));
         object.insert("key".to_string(), Value::Object(nested_object));
         assert_eq!(value, Value::Object(object));
@@ -257,24 +210,20 @@ mod test {
     #[test]
     fn test_parse_array() {
         let json = r#"[null, 1, true, "monkey-json"]"#;
-        let value = Parser::new(Lexer::new(json).tokenize().unwrap())
-            .parse()
-            .unwrap();
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
         let array = Value::Array(vec![
             Value::Null,
-            Value::Number(1.0),
+            Value::Number(1.into()),
             Value::Bool(true),
             Value::String("monkey-json".to_string()),
         ]);
         assert_eq!(value, array);
 
         let json = r#"[["togatoga", 123]]"#;
-        let value = Parser::new(Lexer::new(json).tokenize().unwrap())
-            .parse()
-            .unwrap();
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
         let array = Value::Array(vec![Value::Array(vec![
             Value::String("togatoga".to_string()),
-            Value::Number(123.0),
+            Value::Number(123.into()),
         ])]);
         assert_eq!(value, array);
     }
@@ -282,24 +231,168 @@ mod test {
     #[test]
     fn test_parse() {
         let json = r#"{"key" : [1, "value"]}"#;
-        let value = Parser::new(Lexer::new(json).tokenize().unwrap())
-            .parse()
-            .unwrap();
-        let mut object = BTreeMap::new();
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
+        let mut object = Map::new();
         object.insert(
             "key".to_string(),
-            Value::Array(vec![Value::Number(1.0), Value::String("value".to_string())]),
+            Value::Array(vec![
+                Value::Number(1.into()),
+                Value::String("value".to_string()),
+            ]),
         );
         assert_eq!(value, Value::Object(object));
 
         let json = r#"[{"key": "value"}]"#;
-        let value = Parser::new(Lexer::new(json).tokenize().unwrap())
-            .parse()
-            .unwrap();
-        let mut object = BTreeMap::new();
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
+        let mut object = Map::new();
         object.insert("key".to_string(), Value::String("value".to_string()));
 
         let array = Value::Array(vec![Value::Object(object)]);
         assert_eq!(value, array);
     }
+
+    #[test]
+    fn test_parse_trailing_tokens() {
+        let json = "[1] [2]";
+        let strict = ParseOptions::strict();
+        let result =
+            Parser::with_options(Lexer::with_options(json, strict), strict).parse_document();
+        assert!(result.is_err());
+
+        // Lenientモードでは残りの`Token`を無視する
+        let value = Parser::new(Lexer::new(json)).parse_document().unwrap();
+        assert_eq!(value, Value::Array(vec![Value::Number(1.into())]));
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let json = "{\"a\": [1,\n 2 3]}";
+        let e = Parser::new(Lexer::new(json)).parse().unwrap_err();
+        assert_eq!(
+            e.position(),
+            Position {
+                line: 2,
+                column: 4,
+                offset: 13
+            }
+        );
+        assert!(e.message().contains("']' or ','"), "{}", e);
+        assert!(e.message().contains("number 3"), "{}", e);
+
+        let json = "{\"a\": 1 ";
+        let e = Parser::new(Lexer::new(json)).parse().unwrap_err();
+        assert_eq!(e.position().offset, 8);
+        assert!(e.message().contains("the end of input"), "{}", e);
+    }
+
+    #[test]
+    fn test_parse_lazy() {
+        // 構文エラーより後ろの入力は字句解析しないので、最初のエラーを返す
+        let json = "[1 2 ".to_string() + &"@".repeat(1 << 20);
+        let e = Parser::new(Lexer::new(&json)).parse().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedToken);
+        assert_eq!(e.position().offset, 3);
+
+        // Lenientモードでは値の後ろの入力を読み込まない
+        let json = "[1] @";
+        let mut lexer = Lexer::new(json);
+        let value = Parser::with_options(&mut lexer, ParseOptions::default())
+            .parse_document()
+            .unwrap();
+        assert_eq!(value, Value::Array(vec![Value::Number(1.into())]));
+        assert_eq!(lexer.position().offset, 3);
+    }
+
+    #[test]
+    fn test_parse_object_key_order() {
+        let json = r#"{"b": 1, "a": {"d": null, "c": null}, "c": 2}"#;
+        let value = Parser::new(Lexer::new(json)).parse().unwrap();
+        let object = match &value {
+            Value::Object(object) => object,
+            _ => panic!("an object is expected"),
+        };
+        assert_eq!(object.keys().collect::<Vec<_>>(), vec!["b", "a", "c"]);
+        assert_eq!(
+            crate::to_string(&value),
+            r#"{"b":1,"a":{"d":null,"c":null},"c":2}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_duplicate_keys() {
+        let json = r#"{"a": 1, "b": 2, "a": 3}"#;
+        let parse = |duplicate_keys| {
+            let options = ParseOptions {
+                duplicate_keys,
+                ..ParseOptions::default()
+            };
+            Parser::with_options(Lexer::new(json), options).parse()
+        };
+        let value = parse(DuplicateKeys::LastWins).unwrap();
+        assert_eq!(crate::to_string(&value), r#"{"a":3,"b":2}"#);
+        let value = parse(DuplicateKeys::FirstWins).unwrap();
+        assert_eq!(crate::to_string(&value), r#"{"a":1,"b":2}"#);
+        assert!(parse(DuplicateKeys::Error).is_err());
+    }
+
+    /// 1バイトずつしか読み込めない入力。最後まで読み込むと`error`を返す
+    struct Trickle<'a> {
+        input: &'a [u8],
+        error: Option<std::io::ErrorKind>,
+    }
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.input.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.input = rest;
+                    Ok(1)
+                }
+                _ => match self.error {
+                    Some(kind) => Err(kind.into()),
+                    None => Ok(0),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_reader() {
+        let json = "{\"key\": [1, \"\u{3042}\\uD83D\\uDE04\", true],\n \"null\": null}";
+        let reader = Trickle {
+            input: json.as_bytes(),
+            error: None,
+        };
+        assert_eq!(
+            crate::parse_reader(reader).unwrap(),
+            crate::parse(json).unwrap()
+        );
+
+        // エラーの位置は文字列からパースした場合と同じ
+        let json = "{\"a\": [1,\n \"\u{3042}\" 3]}";
+        let reader = Trickle {
+            input: json.as_bytes(),
+            error: None,
+        };
+        let e = crate::parse_reader_with(reader, ParseOptions::strict()).unwrap_err();
+        assert_eq!(
+            e,
+            crate::parse_with(json, ParseOptions::strict()).unwrap_err()
+        );
+
+        // 読み込みのエラーは入力の終端ではなくI/Oエラーとして返す
+        let reader = Trickle {
+            input: b"[1, 2",
+            error: Some(std::io::ErrorKind::ConnectionReset),
+        };
+        let e = crate::parse_reader(reader).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Io);
+        assert_eq!(e.position().offset, 5);
+
+        // 不正なUTF-8はI/Oエラーと区別する
+        let e = crate::parse_reader(&b"[\"\xff\"]"[..]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.position().offset, 2);
+    }
 }
@@ -0,0 +1,461 @@
+use std::fmt;
+
+use crate::{ConvertError, FromValue, Map, PointerError, ToValue, Value};
+
+/// JSON Patch(RFC 6902)の操作
+/// https://www.rfc-editor.org/rfc/rfc6902#section-4
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    /// `path`に`value`を追加する。`Object`のkeyが既に存在すれば置き換える
+    Add { path: String, value: Value },
+    /// `path`の値を削除する
+    Remove { path: String },
+    /// `path`の値を`value`に置き換える。`path`が存在しない場合はエラー
+    Replace { path: String, value: Value },
+    /// `from`の値を削除して`path`に追加する
+    Move { from: String, path: String },
+    /// `from`の値を`path`に追加する
+    Copy { from: String, path: String },
+    /// `path`の値が`value`と等しいことを確かめる
+    Test { path: String, value: Value },
+}
+
+impl PatchOperation {
+    /// `op`の名前を返す
+    /// e.g. add
+    pub fn op(&self) -> &'static str {
+        match self {
+            PatchOperation::Add { .. } => "add",
+            PatchOperation::Remove { .. } => "remove",
+            PatchOperation::Replace { .. } => "replace",
+            PatchOperation::Move { .. } => "move",
+            PatchOperation::Copy { .. } => "copy",
+            PatchOperation::Test { .. } => "test",
+        }
+    }
+
+    /// 操作の対象のJSON Pointerを返す
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Move { path, .. }
+            | PatchOperation::Copy { path, .. }
+            | PatchOperation::Test { path, .. } => path,
+        }
+    }
+
+    /// `value`に操作を1つ適用する。`Value::apply_patch`と異なり`value`を複製しないため、
+    /// 失敗した場合は`value`が途中まで変更されていることがある。エラーの添字は0になる
+    pub fn apply_to(&self, value: &mut Value) -> Result<(), PatchError> {
+        self.apply(value)
+            .map_err(|cause| PatchError::new(cause, 0, self.op()))
+    }
+
+    /// `value`に操作を1つ適用する。失敗した場合は`value`が途中まで変更されていることがある
+    fn apply(&self, value: &mut Value) -> Result<(), PatchErrorCause> {
+        match self {
+            PatchOperation::Add { path, value: v } => {
+                value.pointer_insert(path, v.clone())?;
+            }
+            PatchOperation::Remove { path } => {
+                value.pointer_remove(path)?;
+            }
+            PatchOperation::Replace { path, value: v } => {
+                // 存在しない場合はエラー。`Object`のkeyの順序を保つためその場で置き換える
+                value.pointer_checked(path)?;
+                *value.pointer_mut(path).expect("checked pointer") = v.clone();
+            }
+            PatchOperation::Move { from, path } => {
+                // 自身の子要素には移動できない
+                if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                    return Err(PatchErrorCause::InvalidMove(format!(
+                        "{:?} cannot be moved into its child {:?}",
+                        from, path
+                    )));
+                }
+                if from != path {
+                    let v = value.pointer_remove(from)?;
+                    value.pointer_insert(path, v)?;
+                } else {
+                    value.pointer_checked(from)?;
+                }
+            }
+            PatchOperation::Copy { from, path } => {
+                let v = value.pointer_checked(from)?.clone();
+                value.pointer_insert(path, v)?;
+            }
+            PatchOperation::Test { path, value: v } => {
+                let actual = value.pointer_checked(path)?;
+                if actual != v {
+                    return Err(PatchErrorCause::TestFailed(format!(
+                        "the value at {:?} is {}, but {} is expected",
+                        path, actual, v
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// e.g. {"op": "move", "from": "/a", "path": "/b"}
+impl FromValue for PatchOperation {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| ConvertError::invalid_type("object", value))?;
+        let string = |key: &str| match object.get(key) {
+            Some(v) => String::from_value(v).map_err(|e| e.at_key(key)),
+            None => Err(ConvertError::missing_field(key)),
+        };
+        // `null`も値として扱うので`Option`では受け取らない
+        let value = || {
+            object
+                .get("value")
+                .cloned()
+                .ok_or_else(|| ConvertError::missing_field("value"))
+        };
+        let op = string("op")?;
+        let operation = match op.as_str() {
+            "add" => PatchOperation::Add {
+                path: string("path")?,
+                value: value()?,
+            },
+            "remove" => PatchOperation::Remove {
+                path: string("path")?,
+            },
+            "replace" => PatchOperation::Replace {
+                path: string("path")?,
+                value: value()?,
+            },
+            "move" => PatchOperation::Move {
+                from: string("from")?,
+                path: string("path")?,
+            },
+            "copy" => PatchOperation::Copy {
+                from: string("from")?,
+                path: string("path")?,
+            },
+            "test" => PatchOperation::Test {
+                path: string("path")?,
+                value: value()?,
+            },
+            _ => {
+                return Err(ConvertError::custom(format!("unknown operation {:?}", op)).at_key("op"))
+            }
+        };
+        Ok(operation)
+    }
+}
+
+impl ToValue for PatchOperation {
+    fn to_value(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("op".to_string(), Value::from(self.op()));
+        match self {
+            PatchOperation::Move { from, .. } | PatchOperation::Copy { from, .. } => {
+                object.insert("from".to_string(), Value::from(from.as_str()));
+            }
+            _ => {}
+        }
+        object.insert("path".to_string(), Value::from(self.path()));
+        match self {
+            PatchOperation::Add { value, .. }
+            | PatchOperation::Replace { value, .. }
+            | PatchOperation::Test { value, .. } => {
+                object.insert("value".to_string(), value.clone());
+            }
+            _ => {}
+        }
+        Value::Object(object)
+    }
+}
+
+/// JSON Patchの適用に失敗した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatchErrorKind {
+    /// JSON Pointerが指す要素が存在しない、もしくは不正なJSON Pointer
+    Pointer,
+    /// `test`の値が一致しない
+    TestFailed,
+    /// `move`で自身の子要素に移動しようとした
+    InvalidMove,
+}
+
+/// 操作の失敗した理由
+enum PatchErrorCause {
+    Pointer(PointerError),
+    TestFailed(String),
+    InvalidMove(String),
+}
+
+impl From<PointerError> for PatchErrorCause {
+    fn from(e: PointerError) -> Self {
+        PatchErrorCause::Pointer(e)
+    }
+}
+
+/// JSON Patchの適用に失敗した場合のエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    kind: PatchErrorKind,
+    index: usize,
+    op: &'static str,
+    msg: String,
+    source: Option<PointerError>,
+}
+
+impl PatchError {
+    fn new(cause: PatchErrorCause, index: usize, op: &'static str) -> PatchError {
+        let (kind, msg, source) = match cause {
+            PatchErrorCause::Pointer(e) => (PatchErrorKind::Pointer, e.to_string(), Some(e)),
+            PatchErrorCause::TestFailed(msg) => (PatchErrorKind::TestFailed, msg, None),
+            PatchErrorCause::InvalidMove(msg) => (PatchErrorKind::InvalidMove, msg, None),
+        };
+        PatchError {
+            kind,
+            index,
+            op,
+            msg,
+            source,
+        }
+    }
+
+    /// エラーの種類を返す
+    pub fn kind(&self) -> PatchErrorKind {
+        self.kind
+    }
+
+    /// 失敗した操作の添字(0始まり)を返す
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// 失敗した操作の`op`を返す
+    /// e.g. replace
+    pub fn op(&self) -> &str {
+        self.op
+    }
+
+    /// 操作の番号を含まないエラーメッセージを返す
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    /// JSON Pointerの操作に失敗した場合はそのエラーを返す
+    pub fn pointer_error(&self) -> Option<&PointerError> {
+        self.source.as_ref()
+    }
+}
+
+/// e.g. operation 1 (remove) failed: "/a": a key "a" is not found
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "operation {} ({}) failed: {}",
+            self.index, self.op, self.msg
+        )
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl Value {
+    /// JSON Patch(RFC 6902)を適用する。いずれかの操作が失敗した場合は何も変更せず、
+    /// 失敗した操作の添字を含むエラーを返す
+    pub fn apply_patch(&mut self, patch: &[PatchOperation]) -> Result<(), PatchError> {
+        let mut patched = self.clone();
+        for (index, operation) in patch.iter().enumerate() {
+            operation
+                .apply(&mut patched)
+                .map_err(|cause| PatchError::new(cause, index, operation.op()))?;
+        }
+        *self = patched;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_value, json, to_string, to_value};
+
+    fn patch(value: Value) -> Vec<PatchOperation> {
+        from_value(&value).unwrap()
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        // https://www.rfc-editor.org/rfc/rfc6902#appendix-A
+        let cases = vec![
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz", "value": "qux"}]),
+                json!({"baz": "qux", "foo": "bar"}),
+            ),
+            (
+                json!({"foo": ["bar", "baz"]}),
+                json!([{"op": "add", "path": "/foo/1", "value": "qux"}]),
+                json!({"foo": ["bar", "qux", "baz"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "remove", "path": "/baz"}]),
+                json!({"foo": "bar"}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "replace", "path": "/baz", "value": "boo"}]),
+                json!({"baz": "boo", "foo": "bar"}),
+            ),
+            (
+                json!({"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}),
+                json!([{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]),
+                json!({"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}),
+            ),
+            (
+                json!({"foo": ["all", "grass", "cows", "eat"]}),
+                json!([{"op": "move", "from": "/foo/1", "path": "/foo/3"}]),
+                json!({"foo": ["all", "cows", "eat", "grass"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": ["a", 2, "c"]}),
+                json!([
+                    {"op": "test", "path": "/baz", "value": "qux"},
+                    {"op": "test", "path": "/foo/1", "value": 2.0}
+                ]),
+                json!({"baz": "qux", "foo": ["a", 2, "c"]}),
+            ),
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/child", "value": {"grandchild": {}}}]),
+                json!({"foo": "bar", "child": {"grandchild": {}}}),
+            ),
+            (
+                json!({"foo": ["bar"]}),
+                json!([{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]),
+                json!({"foo": ["bar", ["abc", "def"]]}),
+            ),
+            (
+                json!({"a": {"b": null}}),
+                json!([
+                    {"op": "copy", "from": "/a", "path": "/c"},
+                    {"op": "replace", "path": "/a/b", "value": 1},
+                    {"op": "move", "from": "/c", "path": "/c"}
+                ]),
+                json!({"a": {"b": 1}, "c": {"b": null}}),
+            ),
+            (
+                json!([1]),
+                json!([{"op": "replace", "path": "", "value": {"root": true}}]),
+                json!({"root": true}),
+            ),
+        ];
+        for (mut document, operations, expected) in cases {
+            let operations = patch(operations);
+            document.apply_patch(&operations).unwrap();
+            assert_eq!(document, expected);
+        }
+    }
+
+    #[test]
+    fn test_replace_keeps_key_order() {
+        let mut document = json!({"a": 1, "b": 2, "c": {"x": 1, "y": 2}});
+        let operations = patch(json!([
+            {"op": "replace", "path": "/a", "value": 9},
+            {"op": "replace", "path": "/c/x", "value": [0]}
+        ]));
+        document.apply_patch(&operations).unwrap();
+        // `==`はkeyの順序を比較しないため、出力で確かめる
+        assert_eq!(to_string(&document), r#"{"a":9,"b":2,"c":{"x":[0],"y":2}}"#);
+    }
+
+    #[test]
+    fn test_apply_patch_error() {
+        let original = json!({"a": [1, 2], "b": "x"});
+        let cases = vec![
+            (
+                json!({"op": "remove", "path": "/c"}),
+                PatchErrorKind::Pointer,
+                "operation 1 (remove) failed: \"/c\": a key \"c\" is not found",
+            ),
+            (
+                json!({"op": "replace", "path": "/a/3", "value": 3}),
+                PatchErrorKind::Pointer,
+                "operation 1 (replace) failed: \"/a/3\": index 3 is out of range (len 3)",
+            ),
+            (
+                json!({"op": "test", "path": "/b", "value": "y"}),
+                PatchErrorKind::TestFailed,
+                "operation 1 (test) failed: the value at \"/b\" is \"x\", but \"y\" is expected",
+            ),
+            (
+                json!({"op": "move", "from": "/a", "path": "/a/0"}),
+                PatchErrorKind::InvalidMove,
+                "operation 1 (move) failed: \"/a\" cannot be moved into its child \"/a/0\"",
+            ),
+            (
+                json!({"op": "copy", "from": "/b/0", "path": "/c"}),
+                PatchErrorKind::Pointer,
+                "operation 1 (copy) failed: \"/b/0\": string has no children",
+            ),
+        ];
+        for (operation, kind, msg) in cases {
+            // 最初の操作が成功していても元の値は変更されない
+            let operations = patch(json!([{"op": "add", "path": "/a/-", "value": 3}, operation]));
+            let mut document = original.clone();
+            let e = document.apply_patch(&operations).unwrap_err();
+            assert_eq!(e.kind(), kind);
+            assert_eq!(e.index(), 1);
+            assert_eq!(e.to_string(), msg);
+            assert_eq!(document, original);
+        }
+    }
+
+    #[test]
+    fn test_apply_to() {
+        let mut document = json!({"a": [1, 2]});
+        for operation in patch(json!([
+            {"op": "move", "from": "/a/0", "path": "/a/-"},
+            {"op": "add", "path": "/b", "value": true}
+        ])) {
+            operation.apply_to(&mut document).unwrap();
+        }
+        assert_eq!(to_string(&document), r#"{"a":[2,1],"b":true}"#);
+        let operation = PatchOperation::Remove {
+            path: "/c".to_string(),
+        };
+        let e = operation.apply_to(&mut document).unwrap_err();
+        assert_eq!(e.kind(), PatchErrorKind::Pointer);
+        assert_eq!(e.index(), 0);
+    }
+
+    #[test]
+    fn test_patch_operation_value() {
+        let operations = json!([
+            {"op": "add", "path": "/a", "value": null},
+            {"op": "move", "from": "/a", "path": "/b"},
+            {"op": "remove", "path": "/b"}
+        ]);
+        assert_eq!(to_value(&patch(operations.clone())), operations);
+
+        let e =
+            from_value::<Vec<PatchOperation>>(&json!([{"op": "add", "path": "/a"}])).unwrap_err();
+        assert_eq!(e.to_string(), "/0: missing field \"value\"");
+        let e = from_value::<Vec<PatchOperation>>(&json!([{"op": "x", "path": "/a"}])).unwrap_err();
+        assert_eq!(e.to_string(), "/0/op: unknown operation \"x\"");
+        let e = from_value::<PatchOperation>(&json!({"op": "copy", "from": 1, "path": "/a"}))
+            .unwrap_err();
+        assert_eq!(e.to_string(), "/from: expected string, found number");
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt;
+
+use crate::Value;
+
+/// JSON Pointerの操作が失敗した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PointerErrorKind {
+    /// JSON Pointerとして不正な文字列
+    /// e.g. `a/b`, `/a~2`
+    Syntax,
+    /// keyが存在しない、もしくは添字が範囲外
+    NotFound,
+    /// `Array`の添字として不正なtoken
+    /// e.g. `/01`, `/x`
+    InvalidIndex,
+    /// `Object`と`Array`以外の子要素を参照した
+    /// e.g. `"abc"`に対する`/0`
+    NotContainer,
+    /// ルートは削除できない
+    RemoveRoot,
+}
+
+/// JSON Pointerの操作に失敗した場合のエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerError {
+    kind: PointerErrorKind,
+    pointer: String,
+    msg: String,
+}
+
+impl PointerError {
+    fn new(kind: PointerErrorKind, pointer: &str, msg: String) -> PointerError {
+        PointerError {
+            kind,
+            pointer: pointer.to_string(),
+            msg,
+        }
+    }
+
+    /// エラーの種類を返す
+    pub fn kind(&self) -> PointerErrorKind {
+        self.kind
+    }
+
+    /// 操作に使ったJSON Pointerを返す
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// JSON Pointerを含まないエラーメッセージを返す
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// e.g. "/a/3": index 3 is out of range (len 2)
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.pointer, self.msg)
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// keyをJSON Pointerのtokenとしてエスケープする
+/// e.g. a/b~c => a~1b~0c
+pub(crate) fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// JSON Pointerをtokenに分割し、エスケープを元に戻す
+/// https://www.rfc-editor.org/rfc/rfc6901#section-4
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    if !pointer.starts_with('/') {
+        return Err(PointerError::new(
+            PointerErrorKind::Syntax,
+            pointer,
+            "a pointer must be empty or start with '/'".to_string(),
+        ));
+    }
+    pointer[1..]
+        .split('/')
+        .map(|token| {
+            let mut result = String::new();
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    result.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => result.push('~'),
+                    Some('1') => result.push('/'),
+                    _ => {
+                        return Err(PointerError::new(
+                            PointerErrorKind::Syntax,
+                            pointer,
+                            format!("an invalid escape in {:?}", token),
+                        ))
+                    }
+                }
+            }
+            Ok(result)
+        })
+        .collect()
+}
+
+/// `Array`の添字として解釈する。先頭の0は許さない
+/// e.g. 0, 12
+fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|b| b.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// 1つ下の要素を返す
+fn child<'v>(value: &'v Value, token: &str) -> Option<&'v Value> {
+    match value {
+        Value::Object(map) => map.get(token),
+        Value::Array(array) => array.get(parse_index(token)?),
+        _ => None,
+    }
+}
+
+impl Value {
+    /// JSON Pointer(RFC 6901)が指す要素を返す。存在しない場合は`None`を返す
+    /// e.g. v.pointer("/spec/containers/0/image")
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        parse_pointer(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, token| child(value, token))
+    }
+
+    /// JSON Pointer(RFC 6901)が指す要素を可変参照で返す。存在しない場合は`None`を返す
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        parse_pointer(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, token| match value {
+                Value::Object(map) => map.get_mut(token),
+                Value::Array(array) => array.get_mut(parse_index(token)?),
+                _ => None,
+            })
+    }
+
+    /// JSON Pointerが指す位置に値を挿入する
+    /// - `Object`の場合はkeyを追加し、既に存在すれば置き換えて古い値を返す
+    /// - `Array`の場合は添字の位置に挿入する。`-`は末尾を表す
+    /// - 空のJSON Pointerはルートを置き換える
+    pub fn pointer_insert(
+        &mut self,
+        pointer: &str,
+        value: Value,
+    ) -> Result<Option<Value>, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => return Ok(Some(std::mem::replace(self, value))),
+        };
+        match self.pointer_parent_mut(pointer, parents)? {
+            Value::Object(map) => Ok(map.insert(last.clone(), value)),
+            Value::Array(array) => {
+                let index = if last == "-" {
+                    array.len()
+                } else {
+                    array_index(pointer, last)?
+                };
+                if index > array.len() {
+                    return Err(out_of_range(pointer, index, array.len()));
+                }
+                array.insert(index, value);
+                Ok(None)
+            }
+            parent => Err(not_container(pointer, parent)),
+        }
+    }
+
+    /// JSON Pointerが指す要素を削除して返す。`Array`の場合は後ろの要素を詰める
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<Value, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => {
+                return Err(PointerError::new(
+                    PointerErrorKind::RemoveRoot,
+                    pointer,
+                    "the root cannot be removed".to_string(),
+                ))
+            }
+        };
+        match self.pointer_parent_mut(pointer, parents)? {
+            Value::Object(map) => map.remove(last).ok_or_else(|| key_not_found(pointer, last)),
+            Value::Array(array) => {
+                let index = array_index(pointer, last)?;
+                if index >= array.len() {
+                    return Err(out_of_range(pointer, index, array.len()));
+                }
+                Ok(array.remove(index))
+            }
+            parent => Err(not_container(pointer, parent)),
+        }
+    }
+
+    /// JSON Pointerが指す要素を返す。存在しない場合は理由を含むエラーを返す
+    pub(crate) fn pointer_checked(&self, pointer: &str) -> Result<&Value, PointerError> {
+        let mut value = self;
+        for token in &parse_pointer(pointer)? {
+            value = match value {
+                Value::Object(map) => map
+                    .get(token)
+                    .ok_or_else(|| key_not_found(pointer, token))?,
+                Value::Array(array) => {
+                    let index = array_index(pointer, token)?;
+                    array
+                        .get(index)
+                        .ok_or_else(|| out_of_range(pointer, index, array.len()))?
+                }
+                value => return Err(not_container(pointer, value)),
+            };
+        }
+        Ok(value)
+    }
+
+    /// `tokens`を辿った要素を返す。途中で見つからない場合はエラー
+    fn pointer_parent_mut(
+        &mut self,
+        pointer: &str,
+        tokens: &[String],
+    ) -> Result<&mut Value, PointerError> {
+        let mut value = self;
+        for token in tokens {
+            value = match value {
+                Value::Object(map) => map
+                    .get_mut(token)
+                    .ok_or_else(|| key_not_found(pointer, token))?,
+                Value::Array(array) => {
+                    let len = array.len();
+                    let index = array_index(pointer, token)?;
+                    array
+                        .get_mut(index)
+                        .ok_or_else(|| out_of_range(pointer, index, len))?
+                }
+                value => return Err(not_container(pointer, value)),
+            };
+        }
+        Ok(value)
+    }
+}
+
+fn array_index(pointer: &str, token: &str) -> Result<usize, PointerError> {
+    parse_index(token).ok_or_else(|| {
+        PointerError::new(
+            PointerErrorKind::InvalidIndex,
+            pointer,
+            format!("{:?} is not an array index", token),
+        )
+    })
+}
+
+fn key_not_found(pointer: &str, key: &str) -> PointerError {
+    PointerError::new(
+        PointerErrorKind::NotFound,
+        pointer,
+        format!("a key {:?} is not found", key),
+    )
+}
+
+fn out_of_range(pointer: &str, index: usize, len: usize) -> PointerError {
+    PointerError::new(
+        PointerErrorKind::NotFound,
+        pointer,
+        format!("index {} is out of range (len {})", index, len),
+    )
+}
+
+fn not_container(pointer: &str, value: &Value) -> PointerError {
+    PointerError::new(
+        PointerErrorKind::NotContainer,
+        pointer,
+        format!("{} has no children", value.type_name()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_pointer() {
+        // https://www.rfc-editor.org/rfc/rfc6901#section-5
+        let v = json!({
+            "foo": ["bar", "baz"],
+            "": 0,
+            "a/b": 1,
+            "c%d": 2,
+            "e^f": 3,
+            "g|h": 4,
+            "i\\j": 5,
+            "k\"l": 6,
+            " ": 7,
+            "m~n": 8
+        });
+        assert_eq!(v.pointer(""), Some(&v));
+        assert_eq!(v.pointer("/foo"), Some(&json!(["bar", "baz"])));
+        assert_eq!(v.pointer("/foo/0"), Some(&json!("bar")));
+        assert_eq!(v.pointer("/"), Some(&json!(0)));
+        assert_eq!(v.pointer("/a~1b"), Some(&json!(1)));
+        assert_eq!(v.pointer("/c%d"), Some(&json!(2)));
+        assert_eq!(v.pointer("/e^f"), Some(&json!(3)));
+        assert_eq!(v.pointer("/g|h"), Some(&json!(4)));
+        assert_eq!(v.pointer("/i\\j"), Some(&json!(5)));
+        assert_eq!(v.pointer("/k\"l"), Some(&json!(6)));
+        assert_eq!(v.pointer("/ "), Some(&json!(7)));
+        assert_eq!(v.pointer("/m~0n"), Some(&json!(8)));
+
+        for pointer in [
+            "foo", "/foo/2", "/foo/01", "/foo/-", "/foo/0/x", "/m~2n", "/x",
+        ] {
+            assert_eq!(v.pointer(pointer), None, "{}", pointer);
+        }
+        assert_eq!(escape_token("m~n/o"), "m~0n~1o");
+    }
+
+    #[test]
+    fn test_pointer_mut() {
+        let mut v = json!({"spec": {"containers": [{"image": "a"}]}});
+        *v.pointer_mut("/spec/containers/0/image").unwrap() = json!("b");
+        assert_eq!(v.pointer("/spec/containers/0/image"), Some(&json!("b")));
+        assert_eq!(v.pointer_mut("/spec/containers/1"), None);
+    }
+
+    #[test]
+    fn test_pointer_insert() {
+        let mut v = json!({"a": [1, 3]});
+        assert_eq!(v.pointer_insert("/a/1", json!(2)), Ok(None));
+        assert_eq!(v.pointer_insert("/a/-", json!(4)), Ok(None));
+        assert_eq!(v.pointer_insert("/a/4", json!(5)), Ok(None));
+        assert_eq!(v.pointer_insert("/b", json!(true)), Ok(None));
+        assert_eq!(v.pointer_insert("/b", json!(false)), Ok(Some(json!(true))));
+        assert_eq!(v, json!({"a": [1, 2, 3, 4, 5], "b": false}));
+
+        let e = v.pointer_insert("/a/6", json!(0)).unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::NotFound);
+        assert_eq!(e.to_string(), "\"/a/6\": index 6 is out of range (len 5)");
+        let e = v.pointer_insert("/x/y", json!(0)).unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::NotFound);
+        let e = v.pointer_insert("/b/c", json!(0)).unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::NotContainer);
+        assert_eq!(e.message(), "boolean has no children");
+        let e = v.pointer_insert("/a/01", json!(0)).unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::InvalidIndex);
+        let e = v.pointer_insert("a", json!(0)).unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::Syntax);
+
+        assert_eq!(
+            v.pointer_insert("", json!(null)),
+            Ok(Some(json!({"a": [1, 2, 3, 4, 5], "b": false})))
+        );
+        assert_eq!(v, json!(null));
+    }
+
+    #[test]
+    fn test_pointer_remove() {
+        let mut v = json!({"a": [1, 2, 3], "b~": {"c": null}});
+        assert_eq!(v.pointer_remove("/a/0"), Ok(json!(1)));
+        assert_eq!(v.pointer_remove("/b~0/c"), Ok(json!(null)));
+        assert_eq!(v, json!({"a": [2, 3], "b~": {}}));
+
+        let e = v.pointer_remove("/a/2").unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::NotFound);
+        let e = v.pointer_remove("/a/-").unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::InvalidIndex);
+        let e = v.pointer_remove("/b~0/c").unwrap_err();
+        assert_eq!(e.to_string(), "\"/b~0/c\": a key \"c\" is not found");
+        let e = v.pointer_remove("").unwrap_err();
+        assert_eq!(e.kind(), PointerErrorKind::RemoveRoot);
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::VecDeque;
+
+use crate::{
+    error::INVALID_UTF8,
+    event::{Event, Grammar, TokenSource},
+    lexer::{Lexer, Span, Token},
+    parser::ValueBuilder,
+    source::ChunkSource,
+    Error, ErrorKind, Mode, ParseOptions, Value,
+};
+
+/// 分割して届く入力(ソケット、パイプ、HTTPのbodyなど)を少しずつ受け取るパーサー
+/// 入力の区切りは文字列やUTF-8の文字、サロゲートペアの途中でも構わない。
+/// 読み込めたイベントや値はすぐに取り出せる
+/// 値の後ろの入力は`parse_with`と同じく扱う(Strictモードではエラー)。`multiple_values`を指定した場合は、
+/// 複数の値が連続する入力(e.g. `{"a":1} {"a":2}`)の値を順に返す
+///
+/// ```
+/// use monkey_json::{PushParser, Value};
+///
+/// let mut parser = PushParser::new().multiple_values();
+/// parser.feed(br#"{"face": "\uD83D"#).unwrap();
+/// assert_eq!(parser.next_value().unwrap(), None);
+/// parser.feed(br#"\uDE04"} [1"#).unwrap();
+/// assert_eq!(parser.next_value().unwrap().unwrap()["face"], Value::from("😄"));
+/// parser.feed(b"2]").unwrap();
+/// parser.finish().unwrap();
+/// assert_eq!(parser.next_value().unwrap(), Some(Value::from(vec![12])));
+/// ```
+pub struct PushParser {
+    // パースのオプション
+    options: ParseOptions,
+    // 入力を`Token`に分割する。末尾の途中までの`Token`は続きが届いた時に続きから読み込む
+    lexer: Lexer<ChunkSource>,
+    // 途中までしか届いていない末尾のUTF-8の文字
+    utf8: Vec<u8>,
+    // JSONの文法の状態
+    grammar: Grammar,
+    // 値の途中まで読み込んでいるかどうか
+    in_value: bool,
+    // まだ取り出されていないイベント
+    events: VecDeque<(Event, Span)>,
+    // `next_value`で組み立て中の値
+    builder: ValueBuilder,
+    // 発生したエラー。以降は同じエラーを返す
+    error: Option<Error>,
+    // `finish`を呼んだかどうか
+    finished: bool,
+    // 複数の値が連続する入力を読み込むかどうか
+    multiple_values: bool,
+}
+
+impl PushParser {
+    /// `PushParser`を返す
+    pub fn new() -> PushParser {
+        PushParser::with_options(ParseOptions::default())
+    }
+
+    /// パースのオプションを受け取り`PushParser`を返す
+    pub fn with_options(options: ParseOptions) -> PushParser {
+        PushParser {
+            options,
+            lexer: Lexer::chunked(options),
+            utf8: vec![],
+            grammar: Grammar::new(options),
+            in_value: false,
+            events: VecDeque::new(),
+            builder: ValueBuilder::new(options.duplicate_keys),
+            error: None,
+            finished: false,
+            multiple_values: false,
+        }
+    }
+
+    /// 複数の値が連続する入力(e.g. `{"a":1} {"a":2}`)を読み込み、値を順に返すようにする
+    pub fn multiple_values(mut self) -> PushParser {
+        self.multiple_values = true;
+        self
+    }
+
+    /// 入力の続きを受け取り、読み込める所まで読み込む
+    /// 末尾の途中までの`Token`は次の入力と合わせて読み込む。`finish`の後に呼んだ場合はエラーを返す
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        self.check()?;
+        if self.finished {
+            let e = Error::new(
+                ErrorKind::TrailingData,
+                "the input is fed after the end of input",
+                self.lexer.position(),
+            );
+            return self.record(Err(e));
+        }
+        let result = self.process(chunk);
+        self.record(result)
+    }
+
+    /// 入力の終端を受け取る。値の途中で終わっている場合や、`multiple_values`を指定せずに値がない場合はエラーを返す
+    /// 2回目以降は何もせず、エラーが発生していればそのエラーを返す
+    pub fn finish(&mut self) -> Result<(), Error> {
+        self.check()?;
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        let result = self.process_end();
+        self.record(result)
+    }
+
+    /// 読み込めたイベントとその範囲を返す。まだ読み込めていない場合は`None`を返す
+    pub fn next_event(&mut self) -> Option<(Event, Span)> {
+        self.events.pop_front()
+    }
+
+    /// 読み込めた値を返す。値全体をまだ読み込めていない場合は`None`を返す
+    /// エラーが発生していた場合は、それまでに読み込めた値を返した後にエラーを返す
+    /// `next_event`でイベントを取り出した場合、その値は組み立てられない
+    pub fn next_value(&mut self) -> Result<Option<Value>, Error> {
+        while let Some((event, span)) = self.events.pop_front() {
+            match self.builder.push(event, span) {
+                Ok(Some(value)) => return Ok(Some(value)),
+                Ok(None) => {}
+                // e.g. 重複したkey。組み立て中の値は続きを読み込めない
+                Err(e) => {
+                    self.events.clear();
+                    return self.record(Err(e)).map(|_| None);
+                }
+            }
+        }
+        self.check().map(|_| None)
+    }
+
+    /// 以前にエラーが発生していた場合はそのエラーを返す
+    fn check(&self) -> Result<(), Error> {
+        match &self.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// エラーを記録して`result`をそのまま返す
+    fn record(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        if let Err(e) = &result {
+            self.error = Some(e.clone());
+        }
+        result
+    }
+
+    /// `chunk`を`Token`に分割して文法の状態を進める
+    fn process(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        if self.ignores_rest() {
+            return Ok(());
+        }
+        // 前回の末尾で途中までしか届いていないUTF-8の文字の続きと合わせて、その文字以降だけを検証する
+        self.utf8.extend_from_slice(chunk);
+        let (valid_len, invalid) = match std::str::from_utf8(&self.utf8) {
+            Ok(_) => (self.utf8.len(), false),
+            Err(e) => (e.valid_up_to(), e.error_len().is_some()),
+        };
+        let text = std::str::from_utf8(&self.utf8[..valid_len]).expect("validated UTF-8");
+        self.lexer.feed(text);
+        self.utf8.drain(..valid_len);
+        self.read_tokens()?;
+        if invalid && !self.ignores_rest() {
+            return Err(self.invalid_utf8());
+        }
+        Ok(())
+    }
+
+    /// 入力の終端を文法の状態に渡す
+    fn process_end(&mut self) -> Result<(), Error> {
+        if self.ignores_rest() {
+            return Ok(());
+        }
+        // 末尾のUTF-8の文字が途中で終わっている
+        if !self.utf8.is_empty() {
+            return Err(self.invalid_utf8());
+        }
+        self.lexer.finish();
+        self.read_tokens()?;
+        // 値が1つの場合は`parse_with`と同じく、値が始まっていない入力(e.g. 空白だけ)もエラー
+        if self.in_value || (!self.multiple_values && !self.grammar.is_done()) {
+            self.push_token(None)?;
+        }
+        Ok(())
+    }
+
+    /// 届いた入力から読み込める`Token`を全て文法の状態に渡す
+    /// 末尾の途中までの`Token`は`Lexer`が保存して、次の入力で続きから読み込む
+    fn read_tokens(&mut self) -> Result<(), Error> {
+        // 値を読み終えた後の入力を読み込まない場合は、その入力の字句解析のエラーも返さない
+        while !self.ignores_rest() {
+            match TokenSource::next_token(&mut self.lexer)? {
+                Some(token) => self.push_token(Some(token))?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// 読み込めた入力の直後を指す不正なUTF-8のエラーを返す
+    fn invalid_utf8(&self) -> Error {
+        Error::new(ErrorKind::InvalidUtf8, INVALID_UTF8, self.lexer.position())
+    }
+
+    /// 値を読み終えた後の入力を読み込まないかどうかを返す
+    /// Lenientモードで値が1つの場合は`parse_with`と同じく値の後ろを読み込まない
+    fn ignores_rest(&self) -> bool {
+        !self.multiple_values && self.options.mode == Mode::Lenient && self.grammar.is_done()
+    }
+
+    /// `Token`を文法の状態に渡してイベントを記録する。`None`は入力の終端を表す
+    fn push_token(&mut self, token: Option<(Token, Span)>) -> Result<(), Error> {
+        // 複数の値を読み込む場合、値を読み終えた後の`Token`は次の値の先頭
+        // それ以外は値の後ろの`Token`として文法のエラーになる
+        if token.is_some() && self.grammar.is_done() && self.multiple_values {
+            self.grammar.reset();
+        }
+        if let Some(event) = self.grammar.push(token, self.lexer.position())? {
+            self.events.push_back(event);
+        }
+        self.in_value = !self.grammar.is_done();
+        Ok(())
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_with, DuplicateKeys};
+
+    /// `chunks`を順に入力して読み込めた値を全て返す。複数の値が連続する入力として読み込む
+    fn push_values(chunks: &[&[u8]], options: ParseOptions) -> Result<Vec<Value>, Error> {
+        push_values_with(PushParser::with_options(options).multiple_values(), chunks)
+    }
+
+    fn push_values_with(mut parser: PushParser, chunks: &[&[u8]]) -> Result<Vec<Value>, Error> {
+        let mut values = vec![];
+        for chunk in chunks {
+            parser.feed(chunk)?;
+            while let Some(value) = parser.next_value()? {
+                values.push(value);
+            }
+        }
+        parser.finish()?;
+        while let Some(value) = parser.next_value()? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    #[test]
+    fn test_split_anywhere() {
+        let json =
+            "{\"s\": \"a\\\"b\\uD83D\\uDE04\u{3042}\", \"n\": [12345, -1.5e10, true, false, null]}";
+        let bytes = json.as_bytes();
+        let expected = parse(json).unwrap();
+        for i in 0..=bytes.len() {
+            let values = push_values(&[&bytes[..i], &bytes[i..]], ParseOptions::strict()).unwrap();
+            assert_eq!(values, vec![expected.clone()], "split at {}", i);
+        }
+        let chunks = bytes.chunks(1).collect::<Vec<_>>();
+        assert_eq!(
+            push_values(&chunks, ParseOptions::strict()).unwrap(),
+            vec![expected]
+        );
+    }
+
+    #[test]
+    fn test_incremental() {
+        let mut parser = PushParser::new();
+        parser.feed(b"{\"a\": [1, ").unwrap();
+        // 届いた分のイベントはすぐに取り出せる
+        let events = std::iter::from_fn(|| parser.next_event())
+            .map(|(event, _)| event)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                Event::StartObject,
+                Event::Key("a".to_string()),
+                Event::StartArray,
+                Event::Number(1.into()),
+            ]
+        );
+
+        // 複数の値は順に返す
+        let values = push_values(&[b"1 [2", b"] \"x", b"\" 3"], ParseOptions::strict()).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Value::from(1),
+                Value::from(vec![2]),
+                Value::from("x"),
+                Value::from(3)
+            ]
+        );
+        assert_eq!(
+            push_values(&[b"", b" \n"], ParseOptions::strict()).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        // エラーの位置は全体をパースした場合と同じ
+        for json in [
+            "[1,\n 2 3]",
+            "[1,\n x]",
+            "{\"a\": tru }",
+            "[\"a\u{01}\"]",
+            "[1, 2",
+        ] {
+            let expected = parse_with(json, ParseOptions::strict()).unwrap_err();
+            let bytes = json.as_bytes();
+            for i in 0..=bytes.len() {
+                let e =
+                    push_values(&[&bytes[..i], &bytes[i..]], ParseOptions::strict()).unwrap_err();
+                assert_eq!(e, expected, "{:?} split at {}", json, i);
+            }
+        }
+
+        // エラーの後は同じエラーを返す
+        let mut parser = PushParser::new();
+        let e = parser.feed(b"[1 2 ").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedToken);
+        assert_eq!(parser.feed(b"]").unwrap_err(), e);
+
+        // 不正なUTF-8
+        let e = push_values(&[b"[\"a\xE3\x81", b"\"]"], ParseOptions::default()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.position().offset, 3);
+        let e = push_values(&[b"\"a\xE3\x81"], ParseOptions::default()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+
+        // `Object`を組み立てる時のエラーも記録する
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::default()
+        };
+        let mut parser = PushParser::with_options(options);
+        parser.feed(br#"{"a": 1, "a": 2}"#).unwrap();
+        let e = parser.next_value().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(parser.next_value().unwrap_err(), e);
+        assert_eq!(parser.feed(b" ").unwrap_err(), e);
+        assert_eq!(parser.finish().unwrap_err(), e);
+    }
+
+    #[test]
+    fn test_single_value() {
+        // Strictモードでは値の後ろの入力はエラー
+        for json in ["[1] 2", "1 2", "{} null", "\"a\"\"b\""] {
+            let expected = parse_with(json, ParseOptions::strict()).unwrap_err();
+            assert_eq!(expected.kind(), ErrorKind::TrailingData);
+            let bytes = json.as_bytes();
+            for i in 0..=bytes.len() {
+                let parser = PushParser::with_options(ParseOptions::strict());
+                let e = push_values_with(parser, &[&bytes[..i], &bytes[i..]]).unwrap_err();
+                assert_eq!(e, expected, "{:?} split at {}", json, i);
+            }
+        }
+        // Lenientモードでは値の後ろの入力を読み込まない
+        let parser = PushParser::with_options(ParseOptions::lenient());
+        let values = push_values_with(parser, &[b"[1] garb", b"age @ \xff"]).unwrap();
+        assert_eq!(values, vec![Value::from(vec![1])]);
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut parser = PushParser::new();
+        parser.feed(b"[1]").unwrap();
+        parser.finish().unwrap();
+        // 2回目以降の`finish`は何もしない
+        parser.finish().unwrap();
+        assert_eq!(parser.next_value().unwrap(), Some(Value::from(vec![1])));
+        // `finish`の後の入力はエラー
+        let e = parser.feed(b"[2]").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TrailingData);
+        assert_eq!(e.position().offset, 3);
+        assert_eq!(parser.finish().unwrap_err(), e);
+        assert_eq!(parser.next_value().unwrap_err(), e);
+
+        // 値がない入力は`parse`と同じくエラー
+        let e = PushParser::new().finish().unwrap_err();
+        assert_eq!(e, parse("").unwrap_err());
+        for options in [ParseOptions::strict(), ParseOptions::lenient()] {
+            let mut parser = PushParser::with_options(options);
+            parser.feed(b" \n").unwrap();
+            assert_eq!(
+                parser.finish().unwrap_err(),
+                parse_with(" \n", options).unwrap_err()
+            );
+        }
+        // 複数の値を読み込む場合は値がなくてもよい
+        let mut parser = PushParser::new().multiple_values();
+        parser.feed(b" ").unwrap();
+        parser.finish().unwrap();
+        assert_eq!(parser.next_value().unwrap(), None);
+    }
+
+    #[test]
+    fn test_partial_token() {
+        // 途中までの数値やリテラル、文字列は続きが届いた時に続きから読み込む
+        let mut parser = PushParser::new();
+        for chunk in [
+            &b"[123"[..],
+            b"456",
+            b", tr",
+            b"ue, \"a\\",
+            b"\"\\u30",
+            b"42\"]",
+        ] {
+            parser.feed(chunk).unwrap();
+        }
+        assert_eq!(
+            parser.next_value().unwrap(),
+            Some(parse(r#"[123456, true, "a\"\u3042"]"#).unwrap())
+        );
+
+        // 大きな文字列を細かく分割しても、届いた入力を一度ずつ読み込むだけで済む
+        let content = "\\\"ab\\u3042\u{1F604}".repeat(1 << 16);
+        let json = format!("[\"{}\"]", content);
+        let mut parser = PushParser::new();
+        for chunk in json.as_bytes().chunks(7) {
+            parser.feed(chunk).unwrap();
+        }
+        parser.finish().unwrap();
+        assert_eq!(parser.next_value().unwrap(), Some(parse(&json).unwrap()));
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt::{self, Write};
+
+use crate::{Number, Value};
+
+/// Pretty出力時のインデント幅(`mj`の出力に合わせる)
+const INDENT_WIDTH: usize = 3;
+
+/// JSON文字列に変換する際のオプション
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    /// `None`なら空白なし、`Some(n)`なら1階層ごとにn文字インデントする
+    pub indent: Option<usize>,
+    /// `Object`のkeyを辞書順に並び替えて出力する。`false`なら挿入順に出力する
+    pub sort_keys: bool,
+}
+
+impl WriteOptions {
+    /// 空白なしで出力するオプションを返す
+    pub fn compact() -> WriteOptions {
+        WriteOptions::default()
+    }
+
+    /// インデント付きで出力するオプションを返す
+    pub fn pretty() -> WriteOptions {
+        WriteOptions {
+            indent: Some(INDENT_WIDTH),
+            sort_keys: false,
+        }
+    }
+}
+
+/// `Value`を空白なしのJSON文字列に変換する
+/// e.g. {"key":[1,true,null]}
+pub fn to_string(value: &Value) -> String {
+    to_string_with(value, WriteOptions::compact())
+}
+
+/// `Value`をインデント付きのJSON文字列に変換する
+/// e.g.
+/// {
+///    "key": [
+///       1,
+///       true
+///    ]
+/// }
+pub fn to_string_pretty(value: &Value) -> String {
+    to_string_with(value, WriteOptions::pretty())
+}
+
+/// `Value`をオプションに従ってJSON文字列に変換する
+pub fn to_string_with(value: &Value, options: WriteOptions) -> String {
+    let mut output = String::new();
+    Serializer::new(options)
+        .write_value(&mut output, value)
+        .expect("a write to String never fails");
+    output
+}
+
+/// `{}`は空白なし、`{:#}`はインデント付きのJSON文字列を出力する
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = if f.alternate() {
+            WriteOptions::pretty()
+        } else {
+            WriteOptions::compact()
+        };
+        Serializer::new(options).write_value(f, self)
+    }
+}
+
+/// `Value`をJSON文字列として書き出す
+struct Serializer {
+    options: WriteOptions,
+}
+
+impl Serializer {
+    fn new(options: WriteOptions) -> Serializer {
+        Serializer { options }
+    }
+
+    /// `Value`を書き出す
+    fn write_value<W: Write>(&self, w: &mut W, value: &Value) -> fmt::Result {
+        self.write_nested(w, value, 0)
+    }
+
+    /// `Value`を再帰的に書き出す。`depth`は現在のネストの深さ
+    fn write_nested<W: Write>(&self, w: &mut W, value: &Value, depth: usize) -> fmt::Result {
+        match value {
+            Value::Null => w.write_str("null"),
+            Value::Bool(b) => write!(w, "{}", b),
+            Value::Number(n) => write_number(w, n),
+            Value::String(s) => write_string(w, s),
+            Value::Array(array) => {
+                if array.is_empty() {
+                    return w.write_str("[]");
+                }
+                w.write_char('[')?;
+                for (i, v) in array.iter().enumerate() {
+                    if i != 0 {
+                        w.write_char(',')?;
+                    }
+                    self.write_newline(w, depth + 1)?;
+                    self.write_nested(w, v, depth + 1)?;
+                }
+                self.write_newline(w, depth)?;
+                w.write_char(']')
+            }
+            Value::Object(object) => {
+                if object.is_empty() {
+                    return w.write_str("{}");
+                }
+                let mut entries = object.iter().collect::<Vec<_>>();
+                if self.options.sort_keys {
+                    entries.sort_by_key(|(k, _)| *k);
+                }
+                w.write_char('{')?;
+                for (i, (k, v)) in entries.into_iter().enumerate() {
+                    if i != 0 {
+                        w.write_char(',')?;
+                    }
+                    self.write_newline(w, depth + 1)?;
+                    write_string(w, k)?;
+                    w.write_char(':')?;
+                    if self.options.indent.is_some() {
+                        w.write_char(' ')?;
+                    }
+                    self.write_nested(w, v, depth + 1)?;
+                }
+                self.write_newline(w, depth)?;
+                w.write_char('}')
+            }
+        }
+    }
+
+    /// Pretty出力の場合のみ改行と`depth`階層分のインデントを書き出す
+    fn write_newline<W: Write>(&self, w: &mut W, depth: usize) -> fmt::Result {
+        match self.options.indent {
+            Some(width) => write!(w, "\n{:indent$}", "", indent = width * depth),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 数値を書き出す。JSONはNaNとInfinityを表現できないので`null`として扱う
+fn write_number<W: Write>(w: &mut W, n: &Number) -> fmt::Result {
+    if n.is_finite() {
+        write!(w, "{}", n)
+    } else {
+        w.write_str("null")
+    }
+}
+
+/// 文字列を'"'で囲み、必要な文字をエスケープして書き出す
+/// https://www.rfc-editor.org/rfc/rfc8259#section-7
+fn write_string<W: Write>(w: &mut W, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            '\u{08}' => w.write_str("\\b")?,
+            '\u{0c}' => w.write_str("\\f")?,
+            // その他の制御文字は\u00XXの形式でエスケープする
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, test_util::Rng, Map};
+
+    #[test]
+    fn test_to_string() {
+        let mut object = Map::new();
+        object.insert(
+            "key".to_string(),
+            Value::Array(vec![
+                Value::Number(1.into()),
+                Value::Number((-0.5).into()),
+                Value::Bool(true),
+                Value::Null,
+            ]),
+        );
+        object.insert("empty".to_string(), Value::Object(Map::new()));
+        let value = Value::Object(object);
+        assert_eq!(
+            to_string(&value),
+            r#"{"key":[1,-0.5,true,null],"empty":{}}"#
+        );
+        assert_eq!(value.to_string(), to_string(&value));
+
+        let options = WriteOptions {
+            sort_keys: true,
+            ..WriteOptions::compact()
+        };
+        assert_eq!(
+            to_string_with(&value, options),
+            r#"{"empty":{},"key":[1,-0.5,true,null]}"#
+        );
+    }
+
+    #[test]
+    fn test_to_string_pretty() {
+        let mut nested = Map::new();
+        nested.insert("b".to_string(), Value::Array(vec![]));
+        let mut object = Map::new();
+        object.insert(
+            "a".to_string(),
+            Value::Array(vec![Value::Number(1.into()), Value::Object(nested)]),
+        );
+        let value = Value::Object(object);
+        let expected = "{\n   \"a\": [\n      1,\n      {\n         \"b\": []\n      }\n   ]\n}";
+        assert_eq!(to_string_pretty(&value), expected);
+        assert_eq!(format!("{:#}", value), expected);
+    }
+
+    #[test]
+    fn test_escape() {
+        let value = Value::String("\"\\\n\r\t\u{08}\u{0c}\u{01}あ".to_string());
+        assert_eq!(to_string(&value), r#""\"\\\n\r\t\b\f\u0001あ""#);
+        assert_eq!(to_string(&Value::Number(f64::NAN.into())), "null");
+        assert_eq!(to_string(&Value::Number(f64::INFINITY.into())), "null");
+    }
+
+    #[test]
+    fn test_preserve_number_literals() {
+        let options = crate::ParseOptions {
+            preserve_number_literals: true,
+            ..crate::ParseOptions::default()
+        };
+        let input = r#"{"price":1.10,"id":1234567890123456789012345678901234567890,"e":-1E+2}"#;
+        let value = crate::parse_with(input, options).unwrap();
+        assert_eq!(to_string(&value), input);
+    }
+
+    fn random_string(rng: &mut Rng) -> String {
+        const CHARS: [char; 12] = [
+            'a', 'Z', '0', ' ', '/', 'あ', '😄', '-', '"', '\\', '\n', '\u{01}',
+        ];
+        (0..rng.below(6))
+            .map(|_| CHARS[rng.below(CHARS.len() as u64) as usize])
+            .collect()
+    }
+
+    fn random_value(rng: &mut Rng, depth: usize) -> Value {
+        let kinds = if depth == 0 { 4 } else { 6 };
+        match rng.below(kinds) {
+            0 => Value::Null,
+            1 => Value::Bool(rng.below(2) == 0),
+            2 => match rng.below(3) {
+                0 => Value::Number(rng.next().into()),
+                1 => Value::Number((rng.next() as i64).into()),
+                _ => loop {
+                    let n = f64::from_bits(rng.next());
+                    if n.is_finite() {
+                        break Value::Number(n.into());
+                    }
+                },
+            },
+            3 => Value::String(random_string(rng)),
+            4 => Value::Array(
+                (0..rng.below(4))
+                    .map(|_| random_value(rng, depth - 1))
+                    .collect(),
+            ),
+            _ => Value::Object(
+                (0..rng.below(4))
+                    .map(|_| (random_string(rng), random_value(rng, depth - 1)))
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..1000 {
+            let value = random_value(&mut rng, 4);
+            assert_eq!(parse(&to_string(&value)).unwrap(), value);
+            assert_eq!(parse(&to_string_pretty(&value)).unwrap(), value);
+        }
+    }
+}
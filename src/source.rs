@@ -0,0 +1,242 @@
+use std::{
+    io::{self, BufRead},
+    iter::Peekable,
+    str::Chars,
+};
+
+/// `Lexer`が読み込む文字の入力元
+pub trait Source {
+    /// 次の文字を読み進めずに返す。入力の終端では`None`を返す
+    fn peek(&mut self) -> Option<char>;
+
+    /// 一文字読み進めて返す。入力の終端では`None`を返す
+    fn next(&mut self) -> Option<char>;
+
+    /// 読み込み中に発生したエラーを取り出す。エラーが発生した後は入力の終端として振る舞う
+    fn take_error(&mut self) -> Option<ReadError> {
+        None
+    }
+
+    /// 入力が全て届いているかどうか。`false`の場合、`peek`が`None`を返しても入力の続きが後から届く
+    fn is_complete(&self) -> bool {
+        true
+    }
+}
+
+/// 入力元から文字を読み込む時のエラー
+#[derive(Debug)]
+pub enum ReadError {
+    /// 読み込みに失敗した
+    Io(io::Error),
+    /// 不正なUTF-8のバイト列を含む
+    InvalidUtf8,
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> ReadError {
+        ReadError::Io(e)
+    }
+}
+
+/// 文字列の入力元
+pub struct StrSource<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> StrSource<'a> {
+    pub fn new(input: &'a str) -> StrSource<'a> {
+        StrSource {
+            chars: input.chars().peekable(),
+        }
+    }
+}
+
+impl Source for StrSource<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next()
+    }
+}
+
+/// 分割して届く文字列の入力元。`finish`を呼ぶまでは入力の続きが後から届くものとして振る舞う
+pub struct ChunkSource {
+    /// 届いた入力のうち、読み込み中のもの
+    text: String,
+    /// 次に読み込む文字の`text`中の位置
+    index: usize,
+    /// 入力が全て届いたかどうか
+    complete: bool,
+}
+
+impl ChunkSource {
+    pub fn new() -> ChunkSource {
+        ChunkSource {
+            text: String::new(),
+            index: 0,
+            complete: false,
+        }
+    }
+
+    /// 入力の続きを追加する。読み込み終えた入力は捨てる
+    pub fn push_str(&mut self, s: &str) {
+        self.text.drain(..self.index);
+        self.index = 0;
+        self.text.push_str(s);
+    }
+
+    /// 入力が全て届いたことを記録する
+    pub fn finish(&mut self) {
+        self.complete = true;
+    }
+}
+
+impl Source for ChunkSource {
+    fn peek(&mut self) -> Option<char> {
+        self.text[self.index..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += c.len_utf8();
+        Some(c)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// `BufRead`の入力元。UTF-8のバイト列を一文字ずつデコードする
+pub struct ReadSource<R> {
+    reader: R,
+    /// 先読みした文字
+    peeked: Option<char>,
+    /// 読み込み中に発生したエラー
+    error: Option<ReadError>,
+    /// エラーが発生したかどうか。発生した後は何も読み込まない
+    failed: bool,
+}
+
+impl<R: BufRead> ReadSource<R> {
+    pub fn new(reader: R) -> ReadSource<R> {
+        ReadSource {
+            reader,
+            peeked: None,
+            error: None,
+            failed: false,
+        }
+    }
+
+    /// 1バイト読み込む
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let buf = loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => break buf,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        let b = match buf.first() {
+            Some(&b) => b,
+            None => return Ok(None),
+        };
+        self.reader.consume(1);
+        Ok(Some(b))
+    }
+
+    /// UTF-8の一文字分のバイト列を読み込んでデコードする
+    fn read_char(&mut self) -> Result<Option<char>, ReadError> {
+        let first = match self.read_byte()? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        let width = utf8_width(first).ok_or(ReadError::InvalidUtf8)?;
+        let mut bytes = [first, 0, 0, 0];
+        for b in bytes.iter_mut().take(width).skip(1) {
+            // 文字の途中で入力が終わっている場合も不正なUTF-8とみなす
+            *b = self.read_byte()?.ok_or(ReadError::InvalidUtf8)?;
+        }
+        let s = std::str::from_utf8(&bytes[..width]).map_err(|_| ReadError::InvalidUtf8)?;
+        Ok(s.chars().next())
+    }
+}
+
+impl<R: BufRead> Source for ReadSource<R> {
+    fn peek(&mut self) -> Option<char> {
+        if self.peeked.is_none() && !self.failed {
+            match self.read_char() {
+                Ok(c) => self.peeked = c,
+                Err(e) => {
+                    self.error = Some(e);
+                    self.failed = true;
+                }
+            }
+        }
+        self.peeked
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.peek();
+        self.peeked.take()
+    }
+
+    fn take_error(&mut self) -> Option<ReadError> {
+        self.error.take()
+    }
+}
+
+/// UTF-8の先頭バイトから文字のバイト数を返す。先頭バイトとして不正な場合は`None`を返す
+/// https://www.rfc-editor.org/rfc/rfc3629#section-4
+fn utf8_width(first: u8) -> Option<usize> {
+    match first {
+        0x00..=0x7F => Some(1),
+        0xC2..=0xDF => Some(2),
+        0xE0..=0xEF => Some(3),
+        0xF0..=0xF4 => Some(4),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_source() {
+        let input = "a\u{3042}\u{1F604}\n";
+        // 1バイトずつしか返さない`BufRead`でも文字の途中で切れない
+        let reader = io::BufReader::with_capacity(1, input.as_bytes());
+        let mut source = ReadSource::new(reader);
+        assert_eq!(source.peek(), Some('a'));
+        let chars = std::iter::from_fn(|| source.next()).collect::<String>();
+        assert_eq!(chars, input);
+        assert!(source.take_error().is_none());
+
+        // 不正なUTF-8はエラーになり、それ以降は終端として扱う
+        let mut source = ReadSource::new(&b"a\xE3\x81"[..]);
+        assert_eq!(source.next(), Some('a'));
+        assert_eq!(source.next(), None);
+        assert!(matches!(source.take_error(), Some(ReadError::InvalidUtf8)));
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn test_chunk_source() {
+        let mut source = ChunkSource::new();
+        source.push_str("a");
+        assert_eq!(source.next(), Some('a'));
+        // 読み込み終えても`finish`までは入力の終端ではない
+        assert_eq!(source.next(), None);
+        assert!(!source.is_complete());
+        source.push_str("\u{3042}b");
+        assert_eq!(source.next(), Some('\u{3042}'));
+        assert_eq!(source.peek(), Some('b'));
+        source.finish();
+        assert!(source.is_complete());
+        assert_eq!(source.next(), Some('b'));
+        assert_eq!(source.next(), None);
+    }
+}
@@ -0,0 +1,503 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{pointer::escape_token, Map, Number, Value};
+
+/// `Value`から変換できる型
+/// e.g. `Vec<u32>`は`[1, 2]`から変換できる
+pub trait FromValue: Sized {
+    /// `Value`から変換する。失敗した場合はJSONのパスを含むエラーを返す
+    fn from_value(value: &Value) -> Result<Self, ConvertError>;
+}
+
+/// `Value`に変換できる型
+pub trait ToValue {
+    /// `Value`に変換する
+    fn to_value(&self) -> Value;
+}
+
+/// 常に`Object`に変換される型
+/// `#[json(tag = "...")]`の`enum`のnewtype variantの中身はこのtraitを実装する必要がある
+pub trait ToObject: ToValue {
+    /// `Object`のkey-valueに変換する
+    fn to_object(&self) -> Map;
+}
+
+/// `Value`を`T`に変換する
+/// e.g. let v: Vec<u32> = from_value(&parse("[1, 2]")?)?;
+pub fn from_value<T: FromValue>(value: &Value) -> Result<T, ConvertError> {
+    T::from_value(value)
+}
+
+/// `T`を`Value`に変換する
+pub fn to_value<T: ToValue + ?Sized>(value: &T) -> Value {
+    value.to_value()
+}
+
+/// `FromValue`の変換に失敗した場合のエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertError {
+    msg: String,
+    /// 失敗した位置までのパス。内側から外側の順に積む
+    path: Vec<String>,
+}
+
+impl ConvertError {
+    /// 任意のメッセージのエラーを返す
+    pub fn custom<T: fmt::Display>(msg: T) -> ConvertError {
+        ConvertError {
+            msg: msg.to_string(),
+            path: vec![],
+        }
+    }
+
+    /// 期待した型と実際の`Value`の型を含むエラーを返す
+    /// e.g. expected number, found string
+    pub fn invalid_type(expected: &str, found: &Value) -> ConvertError {
+        ConvertError::custom(format!(
+            "expected {}, found {}",
+            expected,
+            found.type_name()
+        ))
+    }
+
+    /// 必須のkeyが存在しない場合のエラーを返す
+    pub fn missing_field(key: &str) -> ConvertError {
+        ConvertError::custom(format!("missing field {:?}", key))
+    }
+
+    /// `Object`のkeyをパスの先頭に追加する
+    pub fn at_key(mut self, key: &str) -> ConvertError {
+        self.path.push(key.to_string());
+        self
+    }
+
+    /// `Array`の添字をパスの先頭に追加する
+    pub fn at_index(mut self, index: usize) -> ConvertError {
+        self.path.push(index.to_string());
+        self
+    }
+
+    /// 失敗した位置をJSON Pointer(RFC 6901)で返す。ルートの場合は空文字列
+    /// e.g. /users/3/age
+    pub fn path(&self) -> String {
+        self.path
+            .iter()
+            .rev()
+            .map(|s| format!("/{}", escape_token(s)))
+            .collect()
+    }
+
+    /// パスを含まないエラーメッセージを返す
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// e.g. /users/3/age: expected number, found string
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.msg)
+        } else {
+            write!(f, "{}: {}", self.path(), self.msg)
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        Ok(value.clone())
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        value
+            .as_bool()
+            .ok_or_else(|| ConvertError::invalid_type("boolean", value))
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        value
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| ConvertError::invalid_type("string", value))
+    }
+}
+
+impl FromValue for char {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        let s = value
+            .as_str()
+            .ok_or_else(|| ConvertError::invalid_type("string", value))?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(ConvertError::custom(format!(
+                "expected a single character, found {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+impl FromValue for Number {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        value
+            .as_number()
+            .cloned()
+            .ok_or_else(|| ConvertError::invalid_type("number", value))
+    }
+}
+
+impl FromValue for Map {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        value
+            .as_object()
+            .cloned()
+            .ok_or_else(|| ConvertError::invalid_type("object", value))
+    }
+}
+
+/// 整数に変換する。範囲外や小数の場合はエラー
+macro_rules! impl_from_value_int {
+    ($($ty:ty),*) => {
+        $(
+            impl FromValue for $ty {
+                fn from_value(value: &Value) -> Result<Self, ConvertError> {
+                    let n = value
+                        .as_number()
+                        .ok_or_else(|| ConvertError::invalid_type("number", value))?;
+                    let converted = match (n.as_i64(), n.as_u64()) {
+                        (Some(i), _) => <$ty>::try_from(i).ok(),
+                        (None, Some(u)) => <$ty>::try_from(u).ok(),
+                        (None, None) => None,
+                    };
+                    converted.ok_or_else(|| {
+                        ConvertError::custom(format!(
+                            "number {} is out of range for {}",
+                            n,
+                            stringify!($ty)
+                        ))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+/// 浮動小数点数は精度が落ちても変換する
+macro_rules! impl_from_value_float {
+    ($($ty:ty),*) => {
+        $(
+            impl FromValue for $ty {
+                fn from_value(value: &Value) -> Result<Self, ConvertError> {
+                    value
+                        .as_number()
+                        .map(|n| n.to_f64_lossy() as $ty)
+                        .ok_or_else(|| ConvertError::invalid_type("number", value))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value_float!(f32, f64);
+
+/// `Null`は`None`に変換する
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Box<T> {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        T::from_value(value).map(Box::new)
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        let array = value
+            .as_array()
+            .ok_or_else(|| ConvertError::invalid_type("array", value))?;
+        array
+            .iter()
+            .enumerate()
+            .map(|(i, v)| T::from_value(v).map_err(|e| e.at_index(i)))
+            .collect()
+    }
+}
+
+/// `Object`の各値を`T`に変換する
+fn object_entries<'v, T: FromValue>(
+    value: &'v Value,
+) -> Result<impl Iterator<Item = Result<(String, T), ConvertError>> + 'v, ConvertError> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| ConvertError::invalid_type("object", value))?;
+    Ok(object.iter().map(|(k, v)| {
+        T::from_value(v)
+            .map(|v| (k.clone(), v))
+            .map_err(|e| e.at_key(k))
+    }))
+}
+
+impl<T: FromValue, S: BuildHasher + Default> FromValue for HashMap<String, T, S> {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        object_entries(value)?.collect()
+    }
+}
+
+impl<T: FromValue> FromValue for BTreeMap<String, T> {
+    fn from_value(value: &Value) -> Result<Self, ConvertError> {
+        object_entries(value)?.collect()
+    }
+}
+
+/// タプルは同じ長さの`Array`から変換する
+macro_rules! impl_tuple {
+    ($len:expr => $($name:ident $index:tt),+) => {
+        impl<$($name: FromValue),+> FromValue for ($($name,)+) {
+            fn from_value(value: &Value) -> Result<Self, ConvertError> {
+                let array = value
+                    .as_array()
+                    .ok_or_else(|| ConvertError::invalid_type("array", value))?;
+                if array.len() != $len {
+                    return Err(ConvertError::custom(format!(
+                        "expected an array of length {}, found length {}",
+                        $len,
+                        array.len()
+                    )));
+                }
+                Ok(($(
+                    $name::from_value(&array[$index]).map_err(|e| e.at_index($index))?,
+                )+))
+            }
+        }
+
+        impl<$($name: ToValue),+> ToValue for ($($name,)+) {
+            fn to_value(&self) -> Value {
+                Value::Array(vec![$(self.$index.to_value()),+])
+            }
+        }
+    };
+}
+
+impl_tuple!(1 => A 0);
+impl_tuple!(2 => A 0, B 1);
+impl_tuple!(3 => A 0, B 1, C 2);
+impl_tuple!(4 => A 0, B 1, C 2, D 3);
+impl_tuple!(5 => A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// `From`で`Value`に変換できる型
+macro_rules! impl_to_value_copy {
+    ($($ty:ty),*) => {
+        $(
+            impl ToValue for $ty {
+                fn to_value(&self) -> Value {
+                    Value::from(*self)
+                }
+            }
+        )*
+    };
+}
+
+impl_to_value_copy!(bool, char, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+impl ToValue for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl ToValue for Number {
+    fn to_value(&self) -> Value {
+        Value::Number(self.clone())
+    }
+}
+
+impl ToValue for Map {
+    fn to_value(&self) -> Value {
+        Value::Object(self.clone())
+    }
+}
+
+impl ToValue for str {
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for &T {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for Box<T> {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+/// `None`は`Null`に変換する
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(v) => v.to_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: ToValue> ToValue for [T] {
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(ToValue::to_value).collect())
+    }
+}
+
+impl<T: ToValue> ToValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        self.as_slice().to_value()
+    }
+}
+
+/// 出力する`Object`のkeyの順序は`From<HashMap>`と同じく反復順に従う
+impl<K: AsRef<str> + Eq + Hash, T: ToValue, S: BuildHasher> ToValue for HashMap<K, T, S> {
+    fn to_value(&self) -> Value {
+        Value::Object(self.to_object())
+    }
+}
+
+impl<K: AsRef<str>, T: ToValue> ToValue for BTreeMap<K, T> {
+    fn to_value(&self) -> Value {
+        Value::Object(self.to_object())
+    }
+}
+
+impl ToObject for Map {
+    fn to_object(&self) -> Map {
+        self.clone()
+    }
+}
+
+impl<T: ToObject + ?Sized> ToObject for &T {
+    fn to_object(&self) -> Map {
+        (**self).to_object()
+    }
+}
+
+impl<T: ToObject + ?Sized> ToObject for Box<T> {
+    fn to_object(&self) -> Map {
+        (**self).to_object()
+    }
+}
+
+impl<K: AsRef<str> + Eq + Hash, T: ToValue, S: BuildHasher> ToObject for HashMap<K, T, S> {
+    fn to_object(&self) -> Map {
+        self.iter()
+            .map(|(k, v)| (k.as_ref().to_string(), v.to_value()))
+            .collect()
+    }
+}
+
+impl<K: AsRef<str>, T: ToValue> ToObject for BTreeMap<K, T> {
+    fn to_object(&self) -> Map {
+        self.iter()
+            .map(|(k, v)| (k.as_ref().to_string(), v.to_value()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, parse};
+
+    #[test]
+    fn test_from_value() {
+        let v = parse(r#"[1, -2, 1.5, "a", true, null, [1, "b"], {"x": [1]}]"#).unwrap();
+        assert_eq!(from_value::<u8>(&v[0]), Ok(1));
+        assert_eq!(from_value::<i32>(&v[1]), Ok(-2));
+        assert_eq!(from_value::<f64>(&v[2]), Ok(1.5));
+        assert_eq!(from_value::<String>(&v[3]), Ok("a".to_string()));
+        assert_eq!(from_value::<char>(&v[3]), Ok('a'));
+        assert_eq!(from_value::<bool>(&v[4]), Ok(true));
+        assert_eq!(from_value::<Option<bool>>(&v[5]), Ok(None));
+        assert_eq!(from_value::<Option<bool>>(&v[4]), Ok(Some(true)));
+        assert_eq!(from_value::<(u8, String)>(&v[6]), Ok((1, "b".to_string())));
+        assert_eq!(from_value::<Box<i64>>(&v[0]), Ok(Box::new(1)));
+        let map = from_value::<BTreeMap<String, Vec<u64>>>(&v[7]).unwrap();
+        assert_eq!(map["x"], vec![1]);
+        let map = from_value::<HashMap<String, Value>>(&v[7]).unwrap();
+        assert_eq!(map["x"], v[7]["x"]);
+
+        // 範囲外や型の不一致はエラー
+        assert!(from_value::<u8>(&v[1]).is_err());
+        assert!(from_value::<i64>(&v[2]).is_err());
+        assert!(from_value::<(u8,)>(&v[6]).is_err());
+        assert_eq!(
+            from_value::<u8>(&parse("256").unwrap())
+                .unwrap_err()
+                .to_string(),
+            "number 256 is out of range for u8"
+        );
+    }
+
+    #[test]
+    fn test_error_path() {
+        let v = parse(r#"{"users": [{"age": 1}, {"age": 2}, {"age": 3}, {"age": "4"}]}"#).unwrap();
+        type Users = HashMap<String, Vec<HashMap<String, u32>>>;
+        let e = from_value::<Users>(&v).unwrap_err();
+        assert_eq!(e.to_string(), "/users/3/age: expected number, found string");
+        assert_eq!(e.path(), "/users/3/age");
+        assert_eq!(e.message(), "expected number, found string");
+
+        let w = json!({"a/b": [[1, null]]});
+        let e = from_value::<BTreeMap<String, Vec<(u8, u8)>>>(&w).unwrap_err();
+        assert_eq!(e.to_string(), "/a~1b/0/1: expected number, found null");
+
+        let e = from_value::<Vec<u8>>(&v).unwrap_err();
+        assert_eq!(e.to_string(), "expected array, found object");
+    }
+
+    #[test]
+    fn test_to_value() {
+        let mut map = BTreeMap::new();
+        map.insert("b", vec![(1, Some("x"))]);
+        map.insert("a", vec![(2, None)]);
+        assert_eq!(
+            to_value(&map),
+            parse(r#"{"a": [[2, null]], "b": [[1, "x"]]}"#).unwrap()
+        );
+        assert_eq!(to_value("s"), Value::String("s".to_string()));
+        assert_eq!(to_value(&Box::new(1.5)), parse("1.5").unwrap());
+
+        let v = parse(r#"{"x": [1, 2], "y": [3]}"#).unwrap();
+        let typed = from_value::<HashMap<String, Vec<u8>>>(&v).unwrap();
+        assert_eq!(to_value(&typed), v);
+    }
+}
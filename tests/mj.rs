@@ -0,0 +1,158 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, Command, Output},
+};
+
+/// テスト用の一時ファイルを作成してパスを返す
+fn temp_file(name: &str, content: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("mj-test-{}-{}", process::id(), name));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+/// `mj`を実行して出力を返す
+fn mj(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mj"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_diff_exit_status() {
+    let a = temp_file("diff-a.json", r#"{"a": [1, 2], "b": 1.0}"#);
+    let b = temp_file("diff-b.json", r#"{"a": [1, 3], "b": 1.00}"#);
+    let invalid = temp_file("diff-invalid.json", r#"{"a": "#);
+    let (a, b, invalid) = (
+        a.to_str().unwrap(),
+        b.to_str().unwrap(),
+        invalid.to_str().unwrap(),
+    );
+
+    // 差分がなければ0
+    let output = mj(&["diff", a, a]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    // 差分があれば1
+    let output = mj(&["diff", a, b]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "~ /a/1: 2 -> 3\n");
+    let output = mj(&["diff", "--color", a, b]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "\x1b[33m~ /a/1: 2 -> 3\x1b[m\n");
+    // エラーの場合は2
+    assert_eq!(mj(&["diff", a, invalid]).status.code(), Some(2));
+    assert_eq!(mj(&["diff", a, "no-such-file.json"]).status.code(), Some(2));
+    assert_eq!(mj(&["diff", a]).status.code(), Some(2));
+    assert_eq!(mj(&["diff", "--unknown", a, b]).status.code(), Some(2));
+}
+
+#[test]
+fn test_diff_key_order() {
+    let a = temp_file("order-a.json", r#"{"a": 1, "b": {"x": 1, "y": 2}}"#);
+    let b = temp_file("order-b.json", r#"{"b": {"y": 2, "x": 1}, "a": 1}"#);
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    // keyの順序だけが異なる場合は差分なし
+    let output = mj(&["diff", a, b]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    // `--preserve-order`を指定した場合は順序の違いも差分とする
+    let output = mj(&["diff", "--preserve-order", a, b]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "~ /b/x -> /b/x~0: 1 (moved)\n~ /b/x~0 -> /b/x: 1 (moved)\n\
+         ~ /a -> /a~0: 1 (moved)\n~ /a~0 -> /a: 1 (moved)\n"
+    );
+}
+
+#[test]
+fn test_diff_patch() {
+    let a = temp_file("patch-a.json", r#"{"a": 1, "b": [true]}"#);
+    let b = temp_file("patch-b.json", r#"{"b": [true, null]}"#);
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    let output = mj(&["diff", "--patch", "--minimize", a, b]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "[{\"op\":\"remove\",\"path\":\"/a\"},{\"op\":\"add\",\"path\":\"/b/1\",\"value\":null}]\n"
+    );
+    let output = mj(&["diff", "--patch", "--minimize", a, a]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "[]\n");
+}
+
+#[test]
+fn test_merge3_driver() {
+    // gitのmerge driverとして`mj merge3 %O %A %B`で呼ばれる
+    let base = temp_file("merge-base.json", r#"{"a": 1, "b": [1], "c": "x"}"#);
+    let ours = temp_file("merge-ours.json", r#"{"a": 2, "b": [1], "c": "x"}"#);
+    let theirs = temp_file("merge-theirs.json", r#"{"a": 1, "b": [1, 2], "c": "x"}"#);
+    let (base, ours, theirs) = (
+        base.to_str().unwrap(),
+        ours.to_str().unwrap(),
+        theirs.to_str().unwrap(),
+    );
+
+    // `--stdout`は`OURS`を書き換えずに出力する
+    let output = mj(&["merge3", "--stdout", base, ours, theirs]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "{\n   \"a\": 2,\n   \"b\": [\n      1,\n      2\n   ],\n   \"c\": \"x\"\n}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(ours).unwrap(),
+        r#"{"a": 2, "b": [1], "c": "x"}"#
+    );
+    // `-p`は`--pointer`と紛らわしいので受け付けない
+    assert_eq!(
+        mj(&["merge3", "-p", base, ours, theirs]).status.code(),
+        Some(2)
+    );
+
+    // 衝突がなければ結果を`OURS`に書き込んで0で終了する
+    let output = mj(&["merge3", base, ours, theirs]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        fs::read_to_string(ours).unwrap(),
+        "{\n   \"a\": 2,\n   \"b\": [\n      1,\n      2\n   ],\n   \"c\": \"x\"\n}\n"
+    );
+
+    // 衝突した場合は衝突マーカーを`OURS`に書き込んで1で終了する
+    let theirs = temp_file("merge-conflict.json", r#"{"a": 1, "b": [1, 2], "c": "y"}"#);
+    let theirs = theirs.to_str().unwrap();
+    fs::write(ours, r#"{"a": 2, "b": [1], "c": "z"}"#).unwrap();
+    let output = mj(&["merge3", base, ours, theirs]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "conflict: /c: ours \"z\", theirs \"y\"\n"
+    );
+    assert_eq!(
+        fs::read_to_string(ours).unwrap(),
+        format!(
+            "<<<<<<< {}\n{}\n=======\n{}\n>>>>>>> {}\n",
+            ours,
+            "{\n   \"a\": 2,\n   \"b\": [\n      1,\n      2\n   ],\n   \"c\": \"z\"\n}",
+            "{\n   \"a\": 2,\n   \"b\": [\n      1,\n      2\n   ],\n   \"c\": \"y\"\n}",
+            theirs
+        )
+    );
+
+    // エラーの場合は2で終了する
+    assert_eq!(
+        mj(&["merge3", base, ours, "no-such-file.json"])
+            .status
+            .code(),
+        Some(2)
+    );
+}